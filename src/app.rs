@@ -1,14 +1,17 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::path::PathBuf;
 
-use egui::{epaint::ColorMode, pos2, ColorImage, Pos2, Shape};
-use image::{imageops, ImageError};
-use log::{debug, error};
-use tes3::esp::{Landscape, Region};
+use egui::ColorImage;
+use image::{ImageError, RgbaImage};
+use log::{debug, warn};
 
-use background::{
-    gamemap::generate_map, heightmap::generate_heightmap, landscape::compute_landscape_image, ptmap::generate_ptmap,
-};
-use overlay::{paths::get_overlay_path_image, regions::get_region_shapes};
+use diff::DiffReport;
+use interior::INTERIOR_IMAGE_SIZE;
+use load_order::ELoadOrderSource;
+use loader::{ELoadState, LoadRequest, Loader};
+use planner::plan_route;
+use seams::SeamsReport;
+use tiles::TileCache;
+use overlay::paths::get_overlay_path_image;
 
 use crate::*;
 
@@ -17,6 +20,10 @@ pub enum ESidePanelView {
     #[default]
     Plugins,
     Cells,
+    Conflicts,
+    Interiors,
+    Regions,
+    Route,
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -26,6 +33,20 @@ pub struct TooltipInfo {
     pub region: String,
     pub cell_name: String,
     pub conflicts: Vec<u64>,
+    /// summary of the changes against the diff base
+    pub diff: Vec<String>,
+    /// mismatched borders of the cell
+    pub seams: Vec<String>,
+    /// difference of the stored world map heights to the vertex heights
+    pub world_map: Vec<String>,
+    /// id, plugin and position of the reference under the pointer
+    pub reference: Vec<String>,
+    /// interiors entered from the cell and exits into other cells
+    pub doors: Vec<String>,
+    /// npc, destination, cost and plugin of the travel routes from the cell
+    pub travel: Vec<String>,
+    /// weather chances of the cell's region
+    pub weather: Vec<String>,
     pub debug: String,
 }
 
@@ -35,48 +56,45 @@ pub struct TemplateApp {
     pub data_files: Option<PathBuf>,
     pub ui_data: SavedData,
 
+    // load order
+    #[serde(default)]
+    pub load_order_source: ELoadOrderSource,
+    /// Morrowind.ini or openmw.cfg, None to use the default location
+    #[serde(default)]
+    pub load_order_config: Option<PathBuf>,
+    /// loose files and archives of the current load order
+    #[serde(skip)]
+    pub vfs: Vfs,
+
     // ui
     #[serde(skip)]
     pub zoom_data: ZoomData,
-    #[serde(skip)]
-    pub dimensions: Dimensions,
 
     // tes3
     #[serde(skip)]
     pub plugins: Option<Vec<PluginViewModel>>,
-
-    // runtime data
     #[serde(skip)]
-    pub land_records: HashMap<CellKey, Landscape>,
+    pub world: WorldData,
+    /// plugins being loaded on a worker thread
     #[serde(skip)]
-    pub ltex_records: HashMap<u32, LandscapeTexture>,
+    pub loader: Option<Loader>,
+    /// the "before" world the current world is compared against
     #[serde(skip)]
-    pub regn_records: HashMap<String, Region>,
+    pub diff_base: Option<WorldData>,
+    /// lowercase name of the interior shown instead of the exterior map
     #[serde(skip)]
-    pub cell_records: HashMap<CellKey, Cell>,
-   
-    // intervention spells
-    #[serde(skip)]
-    pub almsivi_interventions: HashMap<CellKey, Cell>,
-    #[serde(skip)]
-    pub divine_interventions: HashMap<CellKey, Cell>,
-    #[serde(skip)]
-    pub kyne_interventions: HashMap<CellKey, Cell>,
+    pub interior: Option<String>,
 
     pub intervention_engine: String,
-    
-    // overlays
-    #[serde(skip)]
-    pub travel_edges: HashMap<String, Vec<(CellKey, CellKey)>>,
-    #[serde(skip)]
-    pub cell_conflicts: HashMap<CellKey, Vec<u64>>,
+
     // textures in memory
     #[serde(skip)]
     pub background_handle: Option<egui::TextureHandle>,
     #[serde(skip)]
     pub paths_handle: Option<egui::TextureHandle>,
+    /// landscape and heightmap tiles
     #[serde(skip)]
-    pub heights: Vec<f32>,
+    pub tiles: TileCache,
     #[serde(skip)]
     pub texture_map_resolution: usize,
     #[serde(skip)]
@@ -104,108 +122,112 @@ impl TemplateApp {
         Default::default()
     }
 
-    pub fn reload_paths(&mut self, ctx: &egui::Context) {
-        let image = get_overlay_path_image(&self.dimensions, &self.land_records);
-        self.paths_handle = Some(ctx.load_texture("paths", image, Default::default()));
+    /// Starts loading the enabled plugins on a worker thread.
+    /// With diff_base the plugins are loaded as the "before" state of a comparison.
+    /// A running load is not replaced, it has to finish or be cancelled first
+    pub fn load_plugins(&mut self, ctx: &egui::Context, diff_base: bool) {
+        let Some(plugins) = &self.plugins else {
+            return;
+        };
+        if self.loader.is_some() {
+            warn!("Another load is still running");
+            return;
+        }
+
+        let request = LoadRequest {
+            plugins: plugins.clone(),
+            settings: self.ui_data.clone(),
+            diff_base,
+        };
+        self.loader = Some(Loader::spawn(ctx, request));
     }
 
-    pub fn populate_texture_map(&mut self, max_texture_side: usize) {
-        // if the resolution is the same, no need to reload
-        let max_texture_resolution = self.dimensions.get_max_texture_resolution(max_texture_side);
-        if max_texture_resolution > self.texture_map_resolution
-            && self.texture_map_resolution == self.ui_data.landscape_settings.texture_size
-        {
-            debug!("Texture resolution is the same, no need to reload");
+    /// Applies the result of a finished load job
+    pub fn poll_loader(&mut self, ctx: &egui::Context) {
+        let Some(loader) = &self.loader else {
             return;
+        };
+
+        match loader.poll() {
+            ELoadState::Running => {}
+            ELoadState::Cancelled => self.loader = None,
+            ELoadState::Done(result) => {
+                self.loader = None;
+
+                let result = *result;
+                if result.diff_base {
+                    self.diff_base = Some(result.world);
+                    self.compare_with_diff_base();
+                    return;
+                }
+
+                self.world = result.world;
+                self.interior = None;
+                self.update_route();
+                self.compare_with_diff_base();
+                self.tiles.reset(&self.ui_data);
+                self.background_handle = result
+                    .background
+                    .map(|image| ctx.load_texture("background", image, Default::default()));
+                self.paths_handle = result
+                    .paths
+                    .map(|image| ctx.load_texture("paths", image, Default::default()));
+            }
         }
+    }
 
-        // otherwise check if possible
-        let cell_size = self.ui_data.landscape_settings.cell_size();
-        let width = self.dimensions.pixel_width(cell_size);
-        let height = self.dimensions.pixel_height(cell_size);
-        if width > max_texture_side || height > max_texture_side {
-            error!(
-                "Texture size too large: (width: {}, height: {}), supported side: {}, max_texture_side: {}",
-                width, height, max_texture_side, max_texture_resolution
-            );
-
-            debug!(
-                "texture_size {}",
-                self.ui_data.landscape_settings.texture_size
-            );
-            debug!("Resetting texture size to 16");
-
-            self.ui_data.landscape_settings.texture_size = 16.min(max_texture_resolution);
-
-            // rfd messagebox
-            let msg = format!(
-                "Texture size too large, supported side: {}",
-                max_texture_resolution
-            );
-            rfd::MessageDialog::new()
-                .set_title("Error")
-                .set_description(msg)
-                .set_buttons(rfd::MessageButtons::Ok)
-                .show();
+    /// Recompute the changed cells of the current world
+    pub fn compare_with_diff_base(&mut self) {
+        if let Some(diff_base) = &self.diff_base {
+            if self.world.plugins.is_empty() {
+                return;
+            }
+            self.world.compare_with(diff_base);
+            self.ui_data.overlay_diff = !self.world.cell_diffs.is_empty();
+        } else {
+            self.world.cell_diffs.clear();
+            self.ui_data.overlay_diff = false;
         }
+    }
+
+    /// Write the changed cells as json
+    pub fn save_diff_report(&self) -> std::io::Result<()> {
+        let Some(diff_base) = &self.diff_base else {
+            return Ok(());
+        };
 
-        self.texture_map.clear();
+        let file_option = rfd::FileDialog::new()
+            .add_filter("json", &["json"])
+            .set_file_name("diff.json")
+            .save_file();
+
+        if let Some(path) = file_option {
+            let report = DiffReport::new(diff_base, &self.world);
+            let json = serde_json::to_string_pretty(&report).map_err(std::io::Error::other)?;
+            std::fs::write(path, json)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn reload_paths(&mut self, ctx: &egui::Context) {
+        let image = get_overlay_path_image(&self.world.dimensions, &self.world.land_records);
+        self.paths_handle = Some(ctx.load_texture("paths", image, Default::default()));
+    }
+
+    /// Load the landscape textures at the texture size of the settings, for images drawn on the cpu.
+    /// Unlike the tiles of the map view they don't have to fit into a gpu texture
+    pub fn populate_texture_map(&mut self) {
         let texture_size = self.ui_data.landscape_settings.texture_size;
-        self.texture_map_resolution = texture_size;
+        if texture_size == self.texture_map_resolution {
+            debug!("Texture resolution is the same, no need to reload");
+            return;
+        }
 
         debug!("Populating texture map with resolution: {}", texture_size);
 
-        for cy in self.dimensions.min_y..self.dimensions.max_y + 1 {
-            for cx in self.dimensions.min_x..self.dimensions.max_x + 1 {
-                if let Some(landscape) = self.land_records.get(&(cx, cy)) {
-                    if landscape
-                        .landscape_flags
-                        .contains(LandscapeFlags::USES_TEXTURES)
-                    {
-                        {
-                            let data = &landscape.texture_indices.data;
-                            for gx in 0..GRID_SIZE {
-                                for gy in 0..GRID_SIZE {
-                                    let dx = (4 * (gy % 4)) + (gx % 4);
-                                    let dy = (4 * (gy / 4)) + (gx / 4);
-
-                                    let key = data[dy][dx] as u32;
-
-                                    // load texture
-                                    if let Some(ltex) = self.ltex_records.get(&key) {
-                                        // texture name
-                                        let texture_name = ltex.file_name.clone();
-                                        if self.texture_map.contains_key(&texture_name) {
-                                            continue;
-                                        }
-
-                                        if let Ok(tex) = load_texture(&self.data_files, ltex) {
-                                            // resize the image
-                                            let image = imageops::resize(
-                                                &tex,
-                                                texture_size as u32,
-                                                texture_size as u32,
-                                                imageops::FilterType::CatmullRom,
-                                            );
-
-                                            // let size = [image.width() as _, image.height() as _];
-                                            // let image_buffer = image.to_rgba8();
-                                            // let pixels = image_buffer.as_flat_samples();
-                                            // return Some(ColorImage::from_rgba_unmultiplied(size, pixels.as_slice()));
-
-                                            info!("Loaded texture: {}", ltex.file_name);
-                                            self.texture_map.insert(texture_name, image);
-                                        } else {
-                                            error!("Failed to load texture: {}", ltex.file_name);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        self.texture_map = self.world.load_texture_map(&self.vfs, texture_size);
+        self.texture_map_resolution = texture_size;
     }
 
     /// Assigns landscape_records, dimensions and pixels
@@ -216,38 +238,22 @@ impl TemplateApp {
         recalculate_dimensions: bool,
         recalculate_heights: bool,
     ) {
-        // calculate dimensions
-        if let Some(dimensions) = new_dimensions {
-            self.dimensions = dimensions.clone();
-        } else if recalculate_dimensions {
-            if let Some(dims) = calculate_dimensions(&self.dimensions, &self.land_records) {
-                self.dimensions = dims;
-            } else {
-                return;
-            }
+        if !self.world.recalculate(new_dimensions, recalculate_dimensions, recalculate_heights) {
+            return;
         }
-
-        // calculate heights
-        if recalculate_heights {
-            if let Some(heights) = calculate_heights(&self.land_records, &mut self.dimensions) {
-                self.heights = heights;
-            }
+        if self.world.heights.is_empty() {
+            self.background_handle = None;
+            return;
         }
 
-        let image = match self.ui_data.background {
-            EBackground::None => None,
-            EBackground::Landscape => {
-                let max_texture_side = ctx.input(|i| i.max_texture_side);
-                self.populate_texture_map(max_texture_side);
-
-                Some(self.get_landscape_image())
-            }
-            EBackground::HeightMap => Some(self.get_heightmap_image()),
-            EBackground::GameMap => Some(self.get_gamemap_image()),
-            EBackground::PTMap => Some(self.get_ptmap_image()),
-        };
+        // tiles are rendered when they are visible
+        self.tiles.clear(&self.ui_data);
+        if self.ui_data.background.is_tiled() {
+            self.background_handle = None;
+            return;
+        }
 
-        if let Some(image) = image {
+        if let Some(image) = self.get_background_image() {
             self.background_handle =
                 Some(ctx.load_texture("background", image, Default::default()));
         } else {
@@ -255,33 +261,14 @@ impl TemplateApp {
         }
     }
 
-    // Shortcuts
-
-    pub fn get_heightmap_image(&mut self) -> ColorImage {
-        generate_heightmap(
-            &self.heights,
-            &self.dimensions,
-            &self.ui_data.heightmap_settings,
-        )
-    }
-
-    pub fn get_gamemap_image(&mut self) -> ColorImage {
-        generate_map(&self.dimensions, &self.land_records)
-    }
-
-    pub fn get_ptmap_image(&mut self) -> ColorImage {
-        generate_ptmap(&self.dimensions, &self.plugins)
-    }
+    /// Generates the image for the currently selected background
+    pub fn get_background_image(&mut self) -> Option<ColorImage> {
+        if self.ui_data.background == EBackground::Landscape {
+            self.populate_texture_map();
+        }
 
-    pub fn get_landscape_image(&mut self) -> ColorImage {
-        compute_landscape_image(
-            &self.ui_data.landscape_settings,
-            &self.dimensions,
-            &self.land_records,
-            &self.ltex_records,
-            &self.heights,
-            &self.texture_map,
-        )
+        self.world
+            .get_background_image(self.ui_data.background, &self.ui_data, &self.texture_map)
     }
 
     // UI methods
@@ -295,14 +282,22 @@ impl TemplateApp {
         self.zoom_data.drag_start = Pos2::default();
     }
 
-    pub fn save_image(&mut self, ctx: &egui::Context) -> Result<(), ImageError> {
-        // construct default name from the first plugin name then the background type abbreviated
+    /// Default export file name: the first plugin name then the background type abbreviated
+    pub fn get_default_image_name(&self) -> String {
+        if let Some(interior) = &self.interior {
+            let name = interior.replace(|c: char| !c.is_alphanumeric(), "_");
+            return format!("{}.png", name);
+        }
+
         let background_name = match self.ui_data.background {
             EBackground::None => "",
             EBackground::Landscape => "l",
             EBackground::HeightMap => "h",
             EBackground::GameMap => "g",
             EBackground::PTMap => "p",
+            EBackground::Hillshade => "hs",
+            EBackground::Slope => "s",
+            EBackground::Aspect => "a",
         };
         let first_plugin = self
             .plugins
@@ -313,7 +308,11 @@ impl TemplateApp {
             .nth(0)
             .unwrap();
         let plugin_name = first_plugin.get_name();
-        let defaultname = format!("{}_{}.png", plugin_name, background_name);
+        format!("{}_{}.png", plugin_name, background_name)
+    }
+
+    pub fn save_image(&mut self) -> Result<(), ImageError> {
+        let defaultname = self.get_default_image_name();
 
         let file_option = rfd::FileDialog::new()
             .add_filter("png", &["png"])
@@ -321,270 +320,177 @@ impl TemplateApp {
             .save_file();
 
         if let Some(original_path) = file_option {
-            let mut background = None;
-            match self.ui_data.background {
-                EBackground::None => {}
-                EBackground::Landscape => {
-                    let max_texture_side = ctx.input(|i| i.max_texture_side);
-                    self.populate_texture_map(max_texture_side);
-                    background = Some(self.get_landscape_image());
-                }
-                EBackground::HeightMap => {
-                    background = Some(self.get_heightmap_image());
-                }
-                EBackground::GameMap => {
-                    background = Some(self.get_gamemap_image());
-                }
-                EBackground::PTMap => {
-                    background = Some(self.get_ptmap_image());
-                }
-            }
+            self.compose_image()?.save(original_path)?;
 
-            if let Some(bg) = background {
-                let mut bg_image = color_image_to_dynamic_image(&bg)?;
-
-                // overlay paths
-                if self.ui_data.overlay_paths {
-                    let fg = get_overlay_path_image(&self.dimensions, &self.land_records);
-                    let mut fg_image = color_image_to_dynamic_image(&fg)?;
-
-                    #[allow(clippy::comparison_chain)]
-                    if bg.size < fg.size {
-                        // resize the smaller image to the larger image
-                        bg_image = imageops::resize(
-                            &bg_image,
-                            fg.size[0] as u32,
-                            fg.size[1] as u32,
-                            imageops::FilterType::CatmullRom,
-                        )
-                        .into();
-                    } else if bg.size > fg.size {
-                        // resize the fg image to the bg image
-                        fg_image = imageops::resize(
-                            &fg_image,
-                            bg.size[0] as u32,
-                            bg.size[1] as u32,
-                            imageops::FilterType::CatmullRom,
-                        )
-                        .into();
-                    }
-
-                    // overlay the images
-                    imageops::overlay(&mut bg_image, &fg_image, 0, 0);
-                }
+            rfd::MessageDialog::new()
+                .set_title("Info")
+                .set_description("Image saved successfully")
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
+        }
 
-                // other overlays
-                let any_overlay = self.ui_data.overlay_region
-                    || self.ui_data.overlay_grid
-                    || self.ui_data.overlay_cities
-                    || self.ui_data.overlay_alm_interventions
-                    || self.ui_data.overlay_div_interventions
-                    || self.ui_data.overlay_kyn_interventions
-                    || self.ui_data.overlay_travel
-                    || self.ui_data.overlay_conflicts;
-
-                if any_overlay {
-                    let real_width = self.dimensions.width() as f32;
-                    let real_height = self.dimensions.height() as f32;
-                    let from: Rect =
-                        Rect::from_min_max(pos2(0.0, 0.0), pos2(real_width, real_height));
-
-                    let canvas_width = bg_image.width() as f32;
-                    let canvas_height = bg_image.height() as f32;
-                    let transform = RectTransform::from_to(
-                        from,
-                        Rect::from_min_max(pos2(0.0, 0.0), pos2(canvas_width, canvas_height)),
-                    );
-
-                    let mut all_shapes = vec![];
-
-                    // order is: paths, regions, grid, interventions, cities, travel, conflicts
-                    // regions
-                    if self.ui_data.overlay_region {
-                        let shapes = get_region_shapes(
-                            transform,
-                            &self.dimensions,
-                            &self.regn_records,
-                            &self.cell_records,
-                        );
-                        all_shapes.extend(shapes);
-                    }
-                    // grid
-                    if self.ui_data.overlay_grid {
-                        let shapes = overlay::grid::get_grid_shapes(transform, &self.dimensions);
-                        all_shapes.extend(shapes);
-                    }
-                    
-                    // interventions
-                    if self.ui_data.overlay_alm_interventions {
-                        let shapes = overlay::interventions::get_intervention_shapes(
-                            transform,
-                            &self.dimensions,
-                            &self.almsivi_interventions,
-                            &self.cell_records,
-                            "almsivi",
-                            &self.intervention_engine,
-                        );
-                        all_shapes.extend(shapes);
-                    }
-                    if self.ui_data.overlay_div_interventions {
-                        let shapes = overlay::interventions::get_intervention_shapes(
-                            transform,
-                            &self.dimensions,
-                            &self.divine_interventions,
-                            &self.cell_records,
-                            "divine",
-                            &self.intervention_engine,
-                        );
-                        all_shapes.extend(shapes);
-                    }
-                    if self.ui_data.overlay_kyn_interventions {
-                        let shapes = overlay::interventions::get_intervention_shapes(
-                            transform,
-                            &self.dimensions,
-                            &self.kyne_interventions,
-                            &self.cell_records,
-                            "kyne",
-                            &self.intervention_engine,
-                        );
-                        all_shapes.extend(shapes);
-                    }
-                    
-                    // cities
-                    if self.ui_data.overlay_cities {
-                        let shapes = overlay::cities::get_cities_shapes(
-                            transform,
-                            &self.dimensions,
-                            &self.cell_records,
-                        );
-                        all_shapes.extend(shapes);
-                    }
-
-                    // travel
-                    if self.ui_data.overlay_travel {
-                        let shapes = overlay::travel::get_travel_shapes(
-                            transform,
-                            &self.dimensions,
-                            &self.travel_edges,
-                        );
-                        all_shapes.extend(shapes);
-                    }
-                    // conflicts
-                    if self.ui_data.overlay_conflicts {
-                        let shapes = overlay::conflicts::get_conflict_shapes(
-                            transform,
-                            &self.dimensions,
-                            &self.cell_conflicts,
-                        );
-                        all_shapes.extend(shapes);
-                    }
-
-                    // draw the shapes
-                    for shape in all_shapes {
-                        match shape {
-                            Shape::Rect(shape) => {
-                                // stroke
-                                if shape.stroke != Default::default() {
-                                    let color = shape.stroke.color;
-                                    let stroke_width = shape.stroke.width;
-                                    let rect = shape.rect;
-
-                                    // image buffer here is just the border of the rectangle with the stroke color and width
-                                    let img = ImageBuffer::from_fn(
-                                        rect.width() as u32,
-                                        rect.height() as u32,
-                                        |x, y| {
-                                            if x < stroke_width as u32
-                                                || x > (rect.width() - stroke_width) as u32
-                                                || y < stroke_width as u32
-                                                || y > (rect.height() - stroke_width) as u32
-                                            {
-                                                image::Rgba([
-                                                    color.r(),
-                                                    color.g(),
-                                                    color.b(),
-                                                    color.a(),
-                                                ])
-                                            } else {
-                                                image::Rgba([0, 0, 0, 0])
-                                            }
-                                        },
-                                    );
-
-                                    imageops::overlay(
-                                        &mut bg_image,
-                                        &img,
-                                        rect.min.x as i64,
-                                        rect.min.y as i64,
-                                    );
-                                } else if shape.fill != Default::default() {
-                                    // filled
-                                    let color = shape.fill;
-                                    let rect = shape.rect;
-
-                                    let img = ImageBuffer::from_pixel(
-                                        rect.width() as u32,
-                                        rect.height() as u32,
-                                        image::Rgba([color.r(), color.g(), color.b(), color.a()]),
-                                    );
-                                    imageops::overlay(
-                                        &mut bg_image,
-                                        &img,
-                                        rect.min.x as i64,
-                                        rect.min.y as i64,
-                                    );
-                                }
-                            }
-                            // TODO fix lines
-                            Shape::LineSegment { points, stroke } => {
-                                if let ColorMode::Solid(color) = stroke.color {
-                                    let stroke_width = stroke.width;
-                                    for i in 0..points.len() - 1 {
-                                        let p1 = points[i];
-                                        let p2 = points[i + 1];
-
-                                        let img = ImageBuffer::from_fn(
-                                            (p1.distance(p2) + 1.0) as u32,
-                                            stroke_width as u32,
-                                            |x, _y| {
-                                                if x < stroke_width as u32 {
-                                                    image::Rgba([
-                                                        color.r(),
-                                                        color.g(),
-                                                        color.b(),
-                                                        color.a(),
-                                                    ])
-                                                } else {
-                                                    image::Rgba([0, 0, 0, 0])
-                                                }
-                                            },
-                                        );
-
-                                        let min = Pos2::new(p1.x.min(p2.x), p1.y.min(p2.y));
-                                        imageops::overlay(
-                                            &mut bg_image,
-                                            &img,
-                                            min.x as i64,
-                                            min.y as i64,
-                                        );
-                                    }
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
+        Ok(())
+    }
+
+    /// Plan the fastest route between the picked start and end cells
+    pub fn update_route(&mut self) {
+        let data = &mut self.runtime_data;
+        data.route = match (data.route_start, data.route_end) {
+            (Some(start), Some(end)) => plan_route(
+                &self.world,
+                start,
+                end,
+                data.route_mark,
+                &self.intervention_engine,
+            ),
+            _ => vec![],
+        };
+    }
+
+    /// Write all landscape seams as json
+    pub fn save_seams_report(&self) -> std::io::Result<()> {
+        let file_option = rfd::FileDialog::new()
+            .add_filter("json", &["json"])
+            .set_file_name("seams.json")
+            .save_file();
 
-                bg_image.save(original_path)?;
+        if let Some(path) = file_option {
+            let report = SeamsReport::new(&self.world);
+            let json = serde_json::to_string_pretty(&report).map_err(std::io::Error::other)?;
+            std::fs::write(path, json)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_svg(&mut self) -> Result<(), ImageError> {
+        let defaultname = self.get_default_image_name().replace(".png", ".svg");
+
+        let file_option = rfd::FileDialog::new()
+            .add_filter("svg", &["svg"])
+            .set_file_name(defaultname)
+            .save_file();
 
-                rfd::MessageDialog::new()
-                    .set_title("Info")
-                    .set_description("Image saved successfully")
-                    .set_buttons(rfd::MessageButtons::Ok)
-                    .show();
+        if let Some(path) = file_option {
+            if self.ui_data.background == EBackground::Landscape {
+                self.populate_texture_map();
             }
+            let svg = match &self.interior {
+                Some(interior) => {
+                    export::svg::compose_interior_svg(&self.world, interior, INTERIOR_IMAGE_SIZE)
+                }
+                None => export::compose_svg(
+                    &self.world,
+                    &self.ui_data,
+                    &self.texture_map,
+                    &self.intervention_engine,
+                )?,
+            };
+            std::fs::write(path, svg)?;
+
+            rfd::MessageDialog::new()
+                .set_title("Info")
+                .set_description("SVG saved successfully")
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
         }
 
         Ok(())
     }
+
+    /// Export the composed image as a {z}/{x}/{y}.png tile pyramid into a folder
+    pub fn save_tiles(&mut self) -> Result<(), ImageError> {
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else {
+            return Ok(());
+        };
+
+        let image = self.compose_image()?;
+        export::export_xyz_tiles(&self.world, &image, &dir, export::xyz::TILE_SIZE)?;
+
+        rfd::MessageDialog::new()
+            .set_title("Info")
+            .set_description("Tiles exported successfully")
+            .set_buttons(rfd::MessageButtons::Ok)
+            .show();
+
+        Ok(())
+    }
+
+    /// Export the vertex heights as 16 bit png, raw 16 bit or raw float with a json sidecar
+    pub fn save_heightmap(&self) -> Result<(), ImageError> {
+        let mut dialog = rfd::FileDialog::new();
+        for format in export::EHeightmapFormat::ALL {
+            dialog = dialog.add_filter(format.label(), &[format.extension()]);
+        }
+        let Some(mut path) = dialog.set_file_name("heightmap.png").save_file() else {
+            return Ok(());
+        };
+
+        let format = export::EHeightmapFormat::from_path(&path).unwrap_or_else(|| {
+            path.set_extension("png");
+            export::EHeightmapFormat::Png16
+        });
+        export::export_heightmap(&self.world, &path, format)?;
+
+        rfd::MessageDialog::new()
+            .set_title("Info")
+            .set_description("Heightmap exported successfully")
+            .set_buttons(rfd::MessageButtons::Ok)
+            .show();
+
+        Ok(())
+    }
+
+    /// Write the heights of an edited heightmap into a new plugin
+    pub fn import_heightmap(&self) -> Result<(), ImageError> {
+        let mut dialog = rfd::FileDialog::new();
+        for format in export::EHeightmapFormat::ALL {
+            dialog = dialog.add_filter(format.label(), &[format.extension()]);
+        }
+        let Some(heightmap) = dialog.pick_file() else {
+            return Ok(());
+        };
+        let Some(output) = rfd::FileDialog::new()
+            .add_filter("esp", &["esp"])
+            .set_file_name("heightmap.esp")
+            .save_file()
+        else {
+            return Ok(());
+        };
+
+        let import = crate::import::import_heightmap(&self.world, &heightmap, &output)?;
+
+        let mut description = format!(
+            "Wrote {} LAND records, masters: {}",
+            import.cells.len(),
+            import.masters.join(", ")
+        );
+        if import.clamped > 0 {
+            description.push_str(&format!(
+                "\n{} vertices were too steep and have been clamped",
+                import.clamped
+            ));
+        }
+        rfd::MessageDialog::new()
+            .set_title("Info")
+            .set_description(description)
+            .set_buttons(rfd::MessageButtons::Ok)
+            .show();
+
+        Ok(())
+    }
+
+    /// Renders the current background with all enabled overlays into one image
+    pub fn compose_image(&mut self) -> Result<RgbaImage, ImageError> {
+        if let Some(interior) = &self.interior {
+            return Ok(self.world.compose_interior_image(interior, INTERIOR_IMAGE_SIZE));
+        }
+
+        if self.ui_data.background == EBackground::Landscape {
+            self.populate_texture_map();
+        }
+
+        self.world
+            .compose_image(&self.ui_data, &self.texture_map, &self.intervention_engine)
+    }
 }
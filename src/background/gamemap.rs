@@ -3,13 +3,31 @@ use std::collections::HashMap;
 use egui::{Color32, ColorImage};
 use tes3::esp::Landscape;
 
-use crate::{CellKey, Dimensions};
+use crate::{get_landscape_heights, get_world_map_data, CellKey, Dimensions, EWorldMapSource};
 
 static GRID: usize = 9;
 
+/// Largest difference in world map steps between the world map heights (WNAM) stored in a
+/// landscape record and the ones calculated from its vertex heights, None without vertex heights
+pub fn get_world_map_delta(land: &Landscape) -> Option<u8> {
+    let expected = get_world_map_data(&get_landscape_heights(land)?);
+    let delta = land
+        .world_map_data
+        .data
+        .iter()
+        .flatten()
+        .zip(expected.iter().flatten())
+        .map(|(a, b)| (*a as i16 - *b as i16).unsigned_abs() as u8)
+        .max()
+        .unwrap_or_default();
+    Some(delta)
+}
+
+/// The game map from the stored world map heights of every cell or calculated from its vertex heights
 pub fn generate_map(
     dimensions: &Dimensions,
     landscape_records: &HashMap<CellKey, Landscape>,
+    source: EWorldMapSource,
 ) -> ColorImage {
     let height = dimensions.pixel_height(GRID);
     let width = dimensions.pixel_width(GRID);
@@ -18,6 +36,17 @@ pub fn generate_map(
     // calculate map size
     let mut pixels: Vec<Color32> = Vec::with_capacity(size);
 
+    // records without vertex heights keep their stored world map heights
+    let calculated: HashMap<CellKey, [[i8; 9]; 9]> = match source {
+        EWorldMapSource::Wnam => HashMap::default(),
+        EWorldMapSource::VertexHeights => landscape_records
+            .iter()
+            .filter_map(|(key, land)| {
+                Some((*key, get_world_map_data(&get_landscape_heights(land)?)))
+            })
+            .collect(),
+    };
+
     for grid_y in 0..height {
         for grid_x in (0..width).rev() {
             // we can divide by grid to get the cell and subtract the bounds to get the cell coordinates
@@ -31,7 +60,7 @@ pub fn generate_map(
                 let hx = grid_x % GRID;
                 let hy = grid_y % GRID;
 
-                let heightmap = land.world_map_data.data.clone().to_vec();
+                let heightmap = calculated.get(&key).unwrap_or(&land.world_map_data.data);
                 pixels.push(get_map_color(heightmap[hy][hx] as f32));
             } else {
                 pixels.push(Color32::TRANSPARENT);
@@ -94,3 +123,35 @@ fn get_map_color(h: f32) -> Color32 {
         pixel_color.b as u8,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use tes3::esp::LandscapeFlags;
+
+    use super::*;
+    use crate::import::encode_vertex_heights;
+
+    #[test]
+    fn world_map_written_by_the_construction_set_is_not_stale() {
+        // rises from 1536 units below sea level in the west to 2560 above in the east
+        let mut heights = [[0.0; 65]; 65];
+        for row in heights.iter_mut() {
+            for (x, z) in row.iter_mut().enumerate() {
+                *z = (x as f32 - 24.0) * 64.0;
+            }
+        }
+        let mut land = Landscape {
+            landscape_flags: LandscapeFlags::USES_VERTEX_HEIGHTS_AND_NORMALS,
+            vertex_heights: encode_vertex_heights(&heights).0,
+            ..Default::default()
+        };
+        *land.world_map_data.data = [[-96, -64, -32, 0, 4, 8, 12, 16, 20]; 9];
+        assert_eq!(get_world_map_delta(&land), Some(0));
+
+        land.world_map_data.data[4][1] = -60;
+        assert_eq!(get_world_map_delta(&land), Some(4));
+
+        land.landscape_flags = LandscapeFlags::empty();
+        assert_eq!(get_world_map_delta(&land), None);
+    }
+}
@@ -1,14 +1,71 @@
 use std::collections::HashMap;
 
 use egui::{Color32, ColorImage};
-use log::info;
+use image::imageops;
+use log::{error, info};
 use tes3::esp::{Landscape, LandscapeFlags, LandscapeTexture};
 
 use crate::{
-    height_from_screen_space, overlay_colors_with_alpha, CellKey, Dimensions, ImageBuffer,
-    LandscapeSettings, DEFAULT_COLOR, GRID_SIZE, VERTEX_CNT,
+    height_from_screen_space, load_texture, overlay_colors_with_alpha, CellKey, Dimensions,
+    ImageBuffer, LandscapeSettings, Vfs, DEFAULT_COLOR, GRID_SIZE, VERTEX_CNT,
 };
 
+/// Load and resize all landscape textures used inside the given dimensions.
+/// Textures already in the texture map are not loaded again
+pub fn load_texture_map(
+    vfs: &Vfs,
+    dimensions: &Dimensions,
+    landscape_records: &HashMap<CellKey, Landscape>,
+    ltex_records: &HashMap<u32, LandscapeTexture>,
+    texture_size: usize,
+    texture_map: &mut HashMap<String, ImageBuffer>,
+) {
+    for cy in dimensions.min_y..dimensions.max_y + 1 {
+        for cx in dimensions.min_x..dimensions.max_x + 1 {
+            if let Some(landscape) = landscape_records.get(&(cx, cy)) {
+                if landscape
+                    .landscape_flags
+                    .contains(LandscapeFlags::USES_TEXTURES)
+                {
+                    let data = &landscape.texture_indices.data;
+                    for gx in 0..GRID_SIZE {
+                        for gy in 0..GRID_SIZE {
+                            let dx = (4 * (gy % 4)) + (gx % 4);
+                            let dy = (4 * (gy / 4)) + (gx / 4);
+
+                            let key = data[dy][dx] as u32;
+
+                            // load texture
+                            if let Some(ltex) = ltex_records.get(&key) {
+                                // texture name
+                                let texture_name = ltex.file_name.clone();
+                                if texture_map.contains_key(&texture_name) {
+                                    continue;
+                                }
+
+                                if let Ok(tex) = load_texture(vfs, ltex) {
+                                    // resize the image
+                                    let image = imageops::resize(
+                                        &tex,
+                                        texture_size as u32,
+                                        texture_size as u32,
+                                        imageops::FilterType::CatmullRom,
+                                    );
+
+                                    info!("Loaded texture: {}", ltex.file_name);
+                                    texture_map.insert(texture_name, image);
+                                } else {
+                                    error!("Failed to load texture: {}", ltex.file_name);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// Compute a landscape image from the given landscape records and texture map.
 pub fn compute_landscape_image(
     settings: &LandscapeSettings,
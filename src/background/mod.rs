@@ -2,3 +2,4 @@ pub mod heightmap;
 pub mod landscape;
 pub mod gamemap;
 pub mod ptmap;
+pub mod terrain;
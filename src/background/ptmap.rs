@@ -52,7 +52,7 @@ pub fn load_image_as_color_image(data: &[u8]) -> Result<ColorImage, ImageError>
 
 pub fn generate_ptmap(
     dimensions: &Dimensions,
-    plugins: &Option<Vec<PluginViewModel>>, 
+    plugins: &[PluginViewModel],
 ) -> ColorImage {
     
     // calculate map size
@@ -81,8 +81,6 @@ pub fn generate_ptmap(
     let mut image_data: ColorImage = ColorImage::new([height, width],Color32::from_gray(0));
 
     let first_plugin = plugins
-        .as_ref()
-        .unwrap()
         .iter()
         .filter(|p| p.enabled)
         .nth(0)
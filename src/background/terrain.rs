@@ -0,0 +1,210 @@
+use eframe::epaint::{Color32, ColorImage};
+use palette::{Hsv, IntoColor, LinSrgb};
+
+use crate::{Dimensions, TerrainSettings, CELL_SIZE, VERTEX_CNT};
+
+/// Game units between two vertices of a cell
+const SPACING: f32 = CELL_SIZE / (VERTEX_CNT - 1) as f32;
+/// Slopes below this many degrees have no aspect
+const FLAT_SLOPE: f32 = 1.0;
+
+/// Height change per game unit to the east and north of every vertex of the heights buffer,
+/// None where there is no landscape. The border vertices of a cell are stored twice,
+/// so the neighbor across a border is two vertices away
+fn get_gradients(heights: &[f32], dimensions: &Dimensions) -> Vec<Option<(f32, f32)>> {
+    let width = dimensions.stride(VERTEX_CNT);
+    let height = dimensions.height() * VERTEX_CNT;
+    let get = |x: usize, y: usize| {
+        heights
+            .get(y * width + x)
+            .copied()
+            .filter(|z| *z >= dimensions.min_z)
+    };
+    let last = VERTEX_CNT - 1;
+
+    let mut gradients = Vec::with_capacity(width * height);
+    for ty in 0..height {
+        for tx in 0..width {
+            let Some(z) = get(tx, ty) else {
+                gradients.push(None);
+                continue;
+            };
+
+            let (lx, ly) = (tx % VERTEX_CNT, ty % VERTEX_CNT);
+            let west = match lx {
+                0 => tx.checked_sub(2),
+                _ => Some(tx - 1),
+            };
+            let east = match lx {
+                l if l == last => Some(tx + 2).filter(|x| *x < width),
+                _ => Some(tx + 1),
+            };
+            // rows go from north to south
+            let north = match ly {
+                0 => ty.checked_sub(2),
+                _ => Some(ty - 1),
+            };
+            let south = match ly {
+                l if l == last => Some(ty + 2).filter(|y| *y < height),
+                _ => Some(ty + 1),
+            };
+
+            // central differences, one sided next to missing landscape
+            let slope = |a: Option<f32>, b: Option<f32>| match (a, b) {
+                (Some(a), Some(b)) => (b - a) / (2.0 * SPACING),
+                (None, Some(b)) => (b - z) / SPACING,
+                (Some(a), None) => (z - a) / SPACING,
+                (None, None) => 0.0,
+            };
+            let dx = slope(west.and_then(|x| get(x, ty)), east.and_then(|x| get(x, ty)));
+            let dy = slope(
+                south.and_then(|y| get(tx, y)),
+                north.and_then(|y| get(tx, y)),
+            );
+            gradients.push(Some((dx, dy)));
+        }
+    }
+    gradients
+}
+
+/// Brightness from 0 to 1 of a surface lit by the sun of the settings
+fn get_shade((dx, dy): (f32, f32), settings: &TerrainSettings) -> f32 {
+    let (dx, dy) = (dx * settings.z_factor, dy * settings.z_factor);
+    let azimuth = settings.azimuth.to_radians();
+    let altitude = settings.altitude.to_radians();
+    let sun = [
+        azimuth.sin() * altitude.cos(),
+        azimuth.cos() * altitude.cos(),
+        altitude.sin(),
+    ];
+    let length = (dx * dx + dy * dy + 1.0).sqrt();
+    ((-dx * sun[0] - dy * sun[1] + sun[2]) / length).max(0.0)
+}
+
+/// Steepness in degrees
+fn get_slope((dx, dy): (f32, f32)) -> f32 {
+    (dx * dx + dy * dy).sqrt().atan().to_degrees()
+}
+
+/// Compass direction in degrees the slope faces, 0 is north and 90 east
+fn get_aspect((dx, dy): (f32, f32)) -> f32 {
+    (-dx).atan2(-dy).to_degrees().rem_euclid(360.0)
+}
+
+fn to_image(dimensions: &Dimensions, pixels: Vec<Color32>) -> ColorImage {
+    ColorImage {
+        size: dimensions.pixel_size_tuple(VERTEX_CNT),
+        pixels,
+    }
+}
+
+/// Analytic hillshade in grayscale
+pub fn generate_hillshade(
+    heights: &[f32],
+    dimensions: &Dimensions,
+    settings: &TerrainSettings,
+) -> ColorImage {
+    let pixels = get_gradients(heights, dimensions)
+        .into_iter()
+        .map(|g| match g {
+            Some(g) => Color32::from_gray((get_shade(g, settings) * 255.0) as u8),
+            None => Color32::TRANSPARENT,
+        })
+        .collect();
+    to_image(dimensions, pixels)
+}
+
+/// Slope in degrees, white for flat ground to dark red at the maximum slope of the settings
+pub fn generate_slope_map(
+    heights: &[f32],
+    dimensions: &Dimensions,
+    settings: &TerrainSettings,
+) -> ColorImage {
+    let stops = [
+        Color32::WHITE,
+        Color32::from_rgb(255, 200, 0),
+        Color32::from_rgb(160, 0, 0),
+    ];
+    let pixels = get_gradients(heights, dimensions)
+        .into_iter()
+        .map(|g| match g {
+            Some(g) => {
+                let t = (get_slope(g) / settings.max_slope.max(1.0)).clamp(0.0, 1.0) * 2.0;
+                let i = (t as usize).min(1);
+                lerp_color(stops[i], stops[i + 1], t - i as f32)
+            }
+            None => Color32::TRANSPARENT,
+        })
+        .collect();
+    to_image(dimensions, pixels)
+}
+
+/// Aspect as hue, red faces north, flat ground is gray
+pub fn generate_aspect_map(heights: &[f32], dimensions: &Dimensions) -> ColorImage {
+    let pixels = get_gradients(heights, dimensions)
+        .into_iter()
+        .map(|g| match g {
+            Some(g) if get_slope(g) < FLAT_SLOPE => Color32::from_gray(160),
+            Some(g) => {
+                let color = Hsv::new(get_aspect(g), 0.8, 0.9);
+                let rgb: LinSrgb = color.into_color();
+                let c: LinSrgb<u8> = rgb.into_format();
+                Color32::from_rgb(c.red, c.green, c.blue)
+            }
+            None => Color32::TRANSPARENT,
+        })
+        .collect();
+    to_image(dimensions, pixels)
+}
+
+/// Darken an image of the cells of dimensions by the hillshade, with the blend opacity of the settings.
+/// The heights may cover more cells around them, so the edges of the image are shaded like the inside
+pub fn blend_hillshade(
+    image: &mut ColorImage,
+    dimensions: &Dimensions,
+    heights: &[f32],
+    heights_dimensions: &Dimensions,
+    settings: &TerrainSettings,
+) {
+    let opacity = settings.hillshade_blend.clamp(0.0, 1.0);
+    if opacity <= 0.0 || heights.is_empty() {
+        return;
+    }
+
+    let gradients = get_gradients(heights, heights_dimensions);
+    let stride = heights_dimensions.stride(VERTEX_CNT);
+    // the nearest vertex of a pixel, images have any number of pixels per cell
+    let to_vertex = |p: usize, size: usize, cells: usize, offset: usize| {
+        let u = (p as f32 + 0.5) / size as f32 * cells as f32;
+        let cell = (u.floor() as usize).min(cells - 1);
+        let local = ((u - cell as f32) * (VERTEX_CNT - 1) as f32).round() as usize;
+        (offset + cell) * VERTEX_CNT + local.min(VERTEX_CNT - 1)
+    };
+    let offset_x = heights_dimensions.tranform_to_canvas_x(dimensions.min_x);
+    let offset_y = heights_dimensions.tranform_to_canvas_y(dimensions.max_y);
+
+    let [width, height] = image.size;
+    for y in 0..height {
+        let ty = to_vertex(y, height, dimensions.height(), offset_y);
+        for x in 0..width {
+            let tx = to_vertex(x, width, dimensions.width(), offset_x);
+            let Some(Some(g)) = gradients.get(ty * stride + tx) else {
+                continue;
+            };
+            let factor = 1.0 - opacity * (1.0 - get_shade(*g, settings));
+            let pixel = &mut image.pixels[y * width + x];
+            let [r, g, b, a] = pixel.to_array();
+            *pixel = Color32::from_rgba_premultiplied(
+                (r as f32 * factor) as u8,
+                (g as f32 * factor) as u8,
+                (b as f32 * factor) as u8,
+                a,
+            );
+        }
+    }
+}
+
+fn lerp_color(a: Color32, b: Color32, t: f32) -> Color32 {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    Color32::from_rgb(mix(a.r(), b.r()), mix(a.g(), b.g()), mix(a.b(), b.b()))
+}
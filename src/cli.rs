@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use image::ImageError;
+use log::{info, warn};
+
+use crate::diff::DiffReport;
+use crate::export::{
+    compose_svg, export_heightmap, export_xyz_tiles, xyz::TILE_SIZE, EHeightmapFormat,
+};
+use crate::load_order::{ELoadOrderSource, LoadOrder};
+use crate::seams::SeamsReport;
+use crate::import::import_heightmap;
+use crate::interior::EObjectType;
+use crate::{
+    EBackground, EOverlay, EWorldMapSource, GameMapSettings, ReferenceFilter, SavedData,
+    TerrainSettings, Vfs, WorldData,
+};
+
+pub const RENDER_USAGE: &str = "Usage: tes3map render --data-files <dir> --plugins <a.esm,b.esp,...> \
+[--morrowind-ini <file> | --openmw-cfg <file>] \
+(--output <file.png> | --tiles <dir> | --svg <file.svg>) [--tile-size <px>] [--background none|gamemap|heightmap|landscape|ptmap|hillshade|slope|aspect] \
+[--overlays paths,regions,weather,contours,coastline,grid,cities,almsivi,divine,kyne,travel,conflicts,diff,seams,wnam,references,doors,labels] \
+[--reference-types npc_,crea,cont,door,acti,ligh,stat] [--reference-id <substring>] \
+[--texture-size <px>] [--sun <azimuth,altitude>] [--hillshade-blend <0-1>] \
+[--world-map-source wnam|heights] [--world-map-tolerance <steps>] [--intervention-engine by-cell|pythagorean] \
+[--diff-base <a.esm,...>] [--diff-report <file.json>] [--seams-report <file.json>] \
+[--heightmap <file.png|file.r16|file.raw>] [--import-heightmap <file> --import-esp <file.esp>]";
+
+/// Arguments of the headless `render` mode
+#[derive(Debug, Clone)]
+pub struct RenderArgs {
+    pub data_files: PathBuf,
+    /// plugins in load order, relative to data_files or absolute
+    pub plugins: Vec<String>,
+    /// where the data directories and archives come from, the plugins are looked up in them
+    pub load_order_source: ELoadOrderSource,
+    /// Morrowind.ini or openmw.cfg
+    pub load_order_config: Option<PathBuf>,
+    pub background: EBackground,
+    pub overlays: Vec<EOverlay>,
+    pub output: Option<PathBuf>,
+    /// folder for a {z}/{x}/{y}.png tile pyramid
+    pub tiles: Option<PathBuf>,
+    pub tile_size: u32,
+    pub svg: Option<PathBuf>,
+    /// plugins of the "before" world the plugins are compared against
+    pub diff_base: Vec<String>,
+    pub diff_report: Option<PathBuf>,
+    pub seams_report: Option<PathBuf>,
+    /// vertex heights as .png, .r16 or .raw with a json sidecar
+    pub heightmap: Option<PathBuf>,
+    /// edited heightmap written into the LAND records of import_esp
+    pub import_heightmap: Option<PathBuf>,
+    pub import_esp: Option<PathBuf>,
+    pub reference_filter: ReferenceFilter,
+    pub texture_size: Option<usize>,
+    pub terrain_settings: TerrainSettings,
+    pub gamemap_settings: GameMapSettings,
+    pub intervention_engine: String,
+}
+
+impl RenderArgs {
+    /// Parse the arguments following `render`
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut data_files = None;
+        let mut plugins = vec![];
+        let mut load_order_source = ELoadOrderSource::default();
+        let mut load_order_config = None;
+        let mut background = EBackground::default();
+        let mut overlays = vec![];
+        let mut output = None;
+        let mut tiles = None;
+        let mut tile_size = TILE_SIZE;
+        let mut svg = None;
+        let mut diff_base = vec![];
+        let mut diff_report = None;
+        let mut seams_report = None;
+        let mut heightmap = None;
+        let mut import_heightmap = None;
+        let mut import_esp = None;
+        let mut reference_filter = ReferenceFilter::default();
+        let mut texture_size = None;
+        let mut terrain_settings = TerrainSettings::default();
+        let mut gamemap_settings = GameMapSettings::default();
+        let mut intervention_engine = String::new();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("Missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--data-files" => data_files = Some(PathBuf::from(value()?)),
+                "--plugins" => plugins.extend(split_list(&value()?)),
+                "--morrowind-ini" => {
+                    load_order_source = ELoadOrderSource::MorrowindIni;
+                    load_order_config = Some(PathBuf::from(value()?));
+                }
+                "--openmw-cfg" => {
+                    load_order_source = ELoadOrderSource::OpenMWCfg;
+                    load_order_config = Some(PathBuf::from(value()?));
+                }
+                "--background" => background = parse_background(&value()?)?,
+                "--overlays" => {
+                    for name in split_list(&value()?) {
+                        overlays.push(parse_overlay(&name)?);
+                    }
+                }
+                "--output" => output = Some(PathBuf::from(value()?)),
+                "--tiles" => tiles = Some(PathBuf::from(value()?)),
+                "--svg" => svg = Some(PathBuf::from(value()?)),
+                "--diff-base" => diff_base.extend(split_list(&value()?)),
+                "--diff-report" => diff_report = Some(PathBuf::from(value()?)),
+                "--seams-report" => seams_report = Some(PathBuf::from(value()?)),
+                "--heightmap" => {
+                    let path = PathBuf::from(value()?);
+                    if EHeightmapFormat::from_path(&path).is_none() {
+                        return Err(format!("Unknown heightmap format: {}", path.display()));
+                    }
+                    heightmap = Some(path);
+                }
+                "--import-heightmap" => import_heightmap = Some(PathBuf::from(value()?)),
+                "--import-esp" => import_esp = Some(PathBuf::from(value()?)),
+                "--reference-types" => {
+                    reference_filter.types.clear();
+                    for tag in split_list(&value()?) {
+                        reference_filter.types.push(parse_object_type(&tag)?);
+                    }
+                }
+                "--reference-id" => reference_filter.id = value()?,
+                "--tile-size" => {
+                    let v = value()?;
+                    tile_size = v
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|size| *size > 0)
+                        .ok_or_else(|| format!("Invalid tile size: {}", v))?;
+                }
+                "--texture-size" => {
+                    let v = value()?;
+                    let size = v
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid texture size: {}", v))?;
+                    texture_size = Some(size);
+                }
+                "--sun" => {
+                    let v = value()?;
+                    let angles = split_list(&v)
+                        .iter()
+                        .map(|a| a.parse::<f32>())
+                        .collect::<Result<Vec<_>, _>>();
+                    match angles.as_deref() {
+                        Ok([azimuth, altitude]) => {
+                            terrain_settings.azimuth = *azimuth;
+                            terrain_settings.altitude = *altitude;
+                        }
+                        _ => return Err(format!("Invalid sun angles: {}", v)),
+                    }
+                }
+                "--hillshade-blend" => {
+                    let v = value()?;
+                    terrain_settings.hillshade_blend = v
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|o| (0.0..=1.0).contains(o))
+                        .ok_or_else(|| format!("Invalid hillshade blend: {}", v))?;
+                }
+                "--world-map-source" => {
+                    gamemap_settings.source = match value()?.to_lowercase().as_str() {
+                        "wnam" => EWorldMapSource::Wnam,
+                        "heights" => EWorldMapSource::VertexHeights,
+                        other => return Err(format!("Unknown world map source: {}", other)),
+                    }
+                }
+                "--world-map-tolerance" => {
+                    let v = value()?;
+                    gamemap_settings.stale_tolerance = v
+                        .parse::<u8>()
+                        .map_err(|_| format!("Invalid world map tolerance: {}", v))?;
+                }
+                "--intervention-engine" => {
+                    intervention_engine = match value()?.to_lowercase().as_str() {
+                        "by-cell" => "By Cell".to_owned(),
+                        "pythagorean" => "Pythagorean".to_owned(),
+                        other => return Err(format!("Unknown intervention engine: {}", other)),
+                    }
+                }
+                other => return Err(format!("Unknown argument: {}", other)),
+            }
+        }
+
+        if plugins.is_empty() {
+            return Err("No plugins given".to_owned());
+        }
+        if diff_report.is_some() && diff_base.is_empty() {
+            return Err("--diff-report needs --diff-base".to_owned());
+        }
+        if import_heightmap.is_some() != import_esp.is_some() {
+            return Err("--import-heightmap and --import-esp go together".to_owned());
+        }
+        if output.is_none()
+            && tiles.is_none()
+            && svg.is_none()
+            && diff_report.is_none()
+            && seams_report.is_none()
+            && heightmap.is_none()
+            && import_esp.is_none()
+        {
+            return Err(
+                "Missing --output, --tiles, --svg, --heightmap, --import-esp or a report".to_owned(),
+            );
+        }
+
+        Ok(Self {
+            data_files: data_files.ok_or("Missing --data-files")?,
+            plugins,
+            load_order_source,
+            load_order_config,
+            background,
+            overlays,
+            output,
+            tiles,
+            tile_size,
+            svg,
+            diff_base,
+            diff_report,
+            seams_report,
+            heightmap,
+            import_heightmap,
+            import_esp,
+            reference_filter,
+            texture_size,
+            terrain_settings,
+            gamemap_settings,
+            intervention_engine,
+        })
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// The plugin in the last data directory of the load order that has it, else in data_files
+fn find_plugin(load_order: &LoadOrder, data_files: &Path, name: &str) -> PathBuf {
+    load_order
+        .data_dirs
+        .iter()
+        .rev()
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| data_files.join(name))
+}
+
+fn parse_background(value: &str) -> Result<EBackground, String> {
+    match value.to_lowercase().as_str() {
+        "none" => Ok(EBackground::None),
+        "landscape" => Ok(EBackground::Landscape),
+        "heightmap" => Ok(EBackground::HeightMap),
+        "gamemap" => Ok(EBackground::GameMap),
+        "ptmap" => Ok(EBackground::PTMap),
+        "hillshade" => Ok(EBackground::Hillshade),
+        "slope" => Ok(EBackground::Slope),
+        "aspect" => Ok(EBackground::Aspect),
+        other => Err(format!("Unknown background: {}", other)),
+    }
+}
+
+fn parse_object_type(value: &str) -> Result<EObjectType, String> {
+    let value = value.to_uppercase();
+    EObjectType::ALL
+        .into_iter()
+        .find(|t| !t.tag().is_empty() && t.tag() == value)
+        .ok_or_else(|| format!("Unknown record type: {}", value))
+}
+
+fn parse_overlay(value: &str) -> Result<EOverlay, String> {
+    let value = value.to_lowercase();
+    EOverlay::ALL
+        .into_iter()
+        .find(|o| o.id() == value)
+        .ok_or_else(|| format!("Unknown overlay: {}", value))
+}
+
+/// Load the given plugins and write the same image "Save as image" would write, without a window.
+/// With --tiles the image is also cut into a web map tile pyramid, --svg writes a layered svg.
+/// With --diff-base the plugins are compared against another set, --diff-report writes the changes.
+/// --seams-report writes the mismatched borders between landscapes, --heightmap the vertex heights.
+/// --import-heightmap writes the heights of an edited heightmap into a new plugin.
+/// With --morrowind-ini or --openmw-cfg the plugins and textures are looked up in the data directories
+/// and archives of the config
+pub fn render(args: &RenderArgs) -> Result<(), ImageError> {
+    // settings
+    let mut settings = SavedData {
+        background: args.background,
+        reference_filter: args.reference_filter.clone(),
+        terrain_settings: args.terrain_settings.clone(),
+        gamemap_settings: args.gamemap_settings.clone(),
+        ..Default::default()
+    };
+    if let Some(texture_size) = args.texture_size {
+        settings.landscape_settings.texture_size = texture_size;
+    }
+    for overlay in &args.overlays {
+        settings.set_overlay_enabled(*overlay, true);
+    }
+
+    // data directories and archives, --data-files without a config
+    let load_order = LoadOrder::from_source(
+        args.load_order_source,
+        args.load_order_config.as_deref(),
+        Some(&args.data_files),
+    )?;
+
+    // plugins in the given order
+    let paths = args
+        .plugins
+        .iter()
+        .map(|p| find_plugin(&load_order, &args.data_files, p))
+        .collect::<Vec<_>>();
+    let mut world = WorldData::from_paths(&paths);
+    if world.heights.is_empty() {
+        return Err(not_found("No landscape records found"));
+    }
+
+    if !args.diff_base.is_empty() {
+        let paths = args
+            .diff_base
+            .iter()
+            .map(|p| find_plugin(&load_order, &args.data_files, p))
+            .collect::<Vec<_>>();
+        let base = WorldData::from_paths(&paths);
+        world.compare_with(&base);
+        info!("{} cells changed", world.cell_diffs.len());
+
+        if let Some(report) = &args.diff_report {
+            let json = serde_json::to_string_pretty(&DiffReport::new(&base, &world))
+                .map_err(|e| ImageError::IoError(std::io::Error::other(e)))?;
+            std::fs::write(report, json)?;
+            info!("Saved diff report to {}", report.display());
+        }
+    }
+    if let Some(report) = &args.seams_report {
+        let json = serde_json::to_string_pretty(&SeamsReport::new(&world))
+            .map_err(|e| ImageError::IoError(std::io::Error::other(e)))?;
+        std::fs::write(report, json)?;
+        info!("Saved {} seams to {}", world.seams.len(), report.display());
+    }
+    if let Some(heightmap) = &args.heightmap {
+        if let Some(format) = EHeightmapFormat::from_path(heightmap) {
+            export_heightmap(&world, heightmap, format)?;
+        }
+    }
+    if let (Some(heightmap), Some(esp)) = (&args.import_heightmap, &args.import_esp) {
+        let import = import_heightmap(&world, heightmap, esp)?;
+        if import.clamped > 0 {
+            warn!("{} vertices were too steep and have been clamped", import.clamped);
+        }
+    }
+
+    if args.output.is_none() && args.tiles.is_none() && args.svg.is_none() {
+        return Ok(());
+    }
+
+    let texture_map = if settings.background == EBackground::Landscape {
+        let vfs = Vfs::from_load_order(&load_order);
+        world.load_texture_map(&vfs, settings.landscape_settings.texture_size)
+    } else {
+        HashMap::default()
+    };
+
+    if let Some(svg) = &args.svg {
+        let text = compose_svg(&world, &settings, &texture_map, &args.intervention_engine)?;
+        std::fs::write(svg, text)?;
+        info!("Saved svg to {}", svg.display());
+        if args.output.is_none() && args.tiles.is_none() {
+            return Ok(());
+        }
+    }
+
+    let image = world.compose_image(&settings, &texture_map, &args.intervention_engine)?;
+    if let Some(output) = &args.output {
+        image.save(output)?;
+        info!("Saved image to {}", output.display());
+    }
+    if let Some(tiles) = &args.tiles {
+        export_xyz_tiles(&world, &image, tiles, args.tile_size)?;
+    }
+    Ok(())
+}
+
+fn not_found(msg: &str) -> ImageError {
+    ImageError::IoError(std::io::Error::new(std::io::ErrorKind::NotFound, msg))
+}
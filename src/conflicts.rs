@@ -0,0 +1,152 @@
+use crate::diff::CellDiff;
+use crate::*;
+
+/// Parts of a CELL or LAND record a plugin can change
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ESubrecord {
+    Name,
+    Region,
+    WaterHeight,
+    References,
+    Heights,
+    Textures,
+    VertexColors,
+}
+
+impl ESubrecord {
+    pub const ALL: [ESubrecord; 7] = [
+        ESubrecord::Name,
+        ESubrecord::Region,
+        ESubrecord::WaterHeight,
+        ESubrecord::References,
+        ESubrecord::Heights,
+        ESubrecord::Textures,
+        ESubrecord::VertexColors,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ESubrecord::Name => "Name",
+            ESubrecord::Region => "Region",
+            ESubrecord::WaterHeight => "Water height",
+            ESubrecord::References => "References",
+            ESubrecord::Heights => "Heights",
+            ESubrecord::Textures => "Textures",
+            ESubrecord::VertexColors => "Vertex colors",
+        }
+    }
+
+    /// Part of the LAND record, otherwise of the CELL record
+    pub fn is_landscape(&self) -> bool {
+        matches!(
+            self,
+            ESubrecord::Heights | ESubrecord::Textures | ESubrecord::VertexColors
+        )
+    }
+}
+
+/// The CELL and LAND records one plugin has for a cell
+#[derive(Debug, Clone, Default)]
+pub struct PluginEdit {
+    pub plugin: u64,
+    pub cell: bool,
+    pub land: bool,
+    /// changes against the records of the plugins loaded before
+    pub diff: CellDiff,
+}
+
+impl PluginEdit {
+    pub fn changes(&self) -> Vec<ESubrecord> {
+        ESubrecord::ALL
+            .into_iter()
+            .filter(|s| self.changes_subrecord(*s))
+            .collect()
+    }
+
+    pub fn changes_subrecord(&self, subrecord: ESubrecord) -> bool {
+        let d = &self.diff;
+        match subrecord {
+            ESubrecord::Name => d.name_changed.is_some(),
+            ESubrecord::Region => d.region_changed.is_some(),
+            ESubrecord::WaterHeight => d.water_height_changed.is_some(),
+            ESubrecord::References => {
+                d.references_added + d.references_removed + d.references_changed > 0
+            }
+            ESubrecord::Heights => d.heights_changed > 0,
+            ESubrecord::Textures => d.textures_changed > 0,
+            ESubrecord::VertexColors => d.vertex_colors_changed > 0,
+        }
+    }
+
+    /// What changed in a subrecord, for tooltips
+    pub fn describe(&self, subrecord: ESubrecord) -> String {
+        let d = &self.diff;
+        match subrecord {
+            ESubrecord::Name => d
+                .name_changed
+                .as_ref()
+                .map(|(a, b)| format!("\"{}\" -> \"{}\"", a, b)),
+            ESubrecord::Region => d
+                .region_changed
+                .as_ref()
+                .map(|(a, b)| format!("\"{}\" -> \"{}\"", a, b)),
+            ESubrecord::WaterHeight => d
+                .water_height_changed
+                .map(|(a, b)| format!("{} -> {}", a, b)),
+            ESubrecord::References => Some(format!(
+                "{} added, {} removed, {} changed",
+                d.references_added, d.references_removed, d.references_changed
+            )),
+            ESubrecord::Heights => Some(format!(
+                "{} vertices, max delta {}",
+                d.heights_changed, d.max_height_delta
+            )),
+            ESubrecord::Textures => Some(format!("{} tiles", d.textures_changed)),
+            ESubrecord::VertexColors => Some(format!("{} vertices", d.vertex_colors_changed)),
+        }
+        .unwrap_or_default()
+    }
+}
+
+/// Get the edit of a plugin for a cell, plugins are expected to be loaded in order
+pub fn get_plugin_edit(
+    edits: &mut HashMap<CellKey, Vec<PluginEdit>>,
+    key: CellKey,
+    plugin: u64,
+) -> &mut PluginEdit {
+    let list = edits.entry(key).or_default();
+    if list.last().map(|e| e.plugin) != Some(plugin) {
+        list.push(PluginEdit {
+            plugin,
+            diff: CellDiff {
+                grid: key,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    }
+    list.last_mut().unwrap()
+}
+
+/// The last plugin to change each subrecord, which is the one whose value ends up in game
+pub fn get_winners(edits: &[PluginEdit]) -> HashMap<ESubrecord, u64> {
+    let mut winners = HashMap::default();
+    for edit in edits {
+        for subrecord in edit.changes() {
+            winners.insert(subrecord, edit.plugin);
+        }
+    }
+    winners
+}
+
+/// Landscape changes of a plugin that a later plugin replaces.
+/// The first plugin is skipped, overriding the master's landscape is what mods do
+pub fn has_overridden_landscape(edits: &[PluginEdit]) -> bool {
+    let winners = get_winners(edits);
+    edits.iter().skip(1).any(|edit| {
+        edit.changes()
+            .into_iter()
+            .filter(|s| s.is_landscape())
+            .any(|s| winners.get(&s) != Some(&edit.plugin))
+    })
+}
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use tes3::esp::{Cell, Landscape, LandscapeFlags, LandscapeTexture, Reference};
+
+use crate::*;
+
+/// What changed in one exterior cell between a "before" and an "after" world
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CellDiff {
+    pub grid: CellKey,
+    pub name: String,
+
+    /// the cell or landscape only exists in the after world
+    pub added: bool,
+    /// the cell or landscape only exists in the before world
+    pub removed: bool,
+
+    /// number of changed vertices and the largest height difference
+    pub heights_changed: usize,
+    pub max_height_delta: f32,
+    pub vertex_colors_changed: usize,
+    /// number of changed texture tiles, out of 256
+    pub textures_changed: usize,
+
+    /// before and after
+    pub name_changed: Option<(String, String)>,
+    pub region_changed: Option<(String, String)>,
+    pub water_height_changed: Option<(f32, f32)>,
+
+    pub references_added: usize,
+    pub references_removed: usize,
+    pub references_changed: usize,
+}
+
+impl CellDiff {
+    pub fn is_empty(&self) -> bool {
+        *self
+            == CellDiff {
+                grid: self.grid,
+                name: self.name.clone(),
+                ..Default::default()
+            }
+    }
+
+    /// One line per kind of change
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = vec![];
+        if self.added {
+            lines.push("Added".to_owned());
+        }
+        if self.removed {
+            lines.push("Removed".to_owned());
+        }
+        if self.heights_changed > 0 {
+            lines.push(format!(
+                "Heights: {} vertices, max delta {}",
+                self.heights_changed, self.max_height_delta
+            ));
+        }
+        if self.vertex_colors_changed > 0 {
+            lines.push(format!(
+                "Vertex colors: {} vertices",
+                self.vertex_colors_changed
+            ));
+        }
+        if self.textures_changed > 0 {
+            lines.push(format!("Textures: {} tiles", self.textures_changed));
+        }
+        if let Some((before, after)) = &self.name_changed {
+            lines.push(format!("Name: \"{}\" -> \"{}\"", before, after));
+        }
+        if let Some((before, after)) = &self.region_changed {
+            lines.push(format!("Region: \"{}\" -> \"{}\"", before, after));
+        }
+        if let Some((before, after)) = &self.water_height_changed {
+            lines.push(format!("Water height: {} -> {}", before, after));
+        }
+        if self.references_added + self.references_removed + self.references_changed > 0 {
+            lines.push(format!(
+                "References: {} added, {} removed, {} changed",
+                self.references_added, self.references_removed, self.references_changed
+            ));
+        }
+        lines
+    }
+}
+
+/// JSON report of a comparison
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiffReport {
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+    pub cells: Vec<CellDiff>,
+}
+
+impl DiffReport {
+    pub fn new(before: &WorldData, after: &WorldData) -> Self {
+        let mut cells = after.cell_diffs.values().cloned().collect::<Vec<_>>();
+        cells.sort_by_key(|c| c.grid);
+
+        Self {
+            before: before.plugins.iter().map(|p| p.get_name()).collect(),
+            after: after.plugins.iter().map(|p| p.get_name()).collect(),
+            cells,
+        }
+    }
+}
+
+/// Compare all exterior cells and landscapes of two worlds
+pub fn diff_worlds(before: &WorldData, after: &WorldData) -> HashMap<CellKey, CellDiff> {
+    let keys = before
+        .cell_records
+        .keys()
+        .chain(before.land_records.keys())
+        .chain(after.cell_records.keys())
+        .chain(after.land_records.keys())
+        .copied()
+        .collect::<HashSet<_>>();
+
+    let mut diffs = HashMap::default();
+    for key in keys {
+        let mut diff = CellDiff {
+            grid: key,
+            ..Default::default()
+        };
+
+        let cell_before = before.cell_records.get(&key);
+        let cell_after = after.cell_records.get(&key);
+        diff.name = cell_after
+            .or(cell_before)
+            .map(|c| c.name.clone())
+            .unwrap_or_default();
+        diff_cells(&mut diff, cell_before, cell_after);
+
+        let land_before = before.land_records.get(&key);
+        let land_after = after.land_records.get(&key);
+        diff_landscapes(
+            &mut diff,
+            (land_before, &before.ltex_records),
+            (land_after, &after.ltex_records),
+        );
+
+        let existed = cell_before.is_some() || land_before.is_some();
+        let exists = cell_after.is_some() || land_after.is_some();
+        diff.added = !existed && exists;
+        diff.removed = existed && !exists;
+
+        if !diff.is_empty() {
+            diffs.insert(key, diff);
+        }
+    }
+    diffs
+}
+
+/// Compare the name, region, water height and references of two versions of a cell
+pub fn diff_cells(diff: &mut CellDiff, before: Option<&Cell>, after: Option<&Cell>) {
+    let empty = Cell::default();
+    let before = before.unwrap_or(&empty);
+    let after = after.unwrap_or(&empty);
+
+    if before.name != after.name {
+        diff.name_changed = Some((before.name.clone(), after.name.clone()));
+    }
+    let region_before = before.region.clone().unwrap_or_default();
+    let region_after = after.region.clone().unwrap_or_default();
+    if region_before != region_after {
+        diff.region_changed = Some((region_before, region_after));
+    }
+    let water_before = before.water_height.unwrap_or_default();
+    let water_after = after.water_height.unwrap_or_default();
+    if water_before != water_after {
+        diff.water_height_changed = Some((water_before, water_after));
+    }
+
+    for (key, reference) in &after.references {
+        match before.references.get(key) {
+            Some(old) if !references_equal(old, reference) => diff.references_changed += 1,
+            Some(_) => {}
+            None => diff.references_added += 1,
+        }
+    }
+    diff.references_removed = before
+        .references
+        .keys()
+        .filter(|key| !after.references.contains_key(key))
+        .count();
+}
+
+fn references_equal(a: &Reference, b: &Reference) -> bool {
+    a.id == b.id
+        && a.translation == b.translation
+        && a.rotation == b.rotation
+        && a.scale == b.scale
+        && a.deleted == b.deleted
+        && a.door_destination_coords == b.door_destination_coords
+        && a.door_destination_cell == b.door_destination_cell
+}
+
+/// Compare the heights, vertex colors and textures of two versions of a landscape
+pub fn diff_landscapes(
+    diff: &mut CellDiff,
+    (before, ltex_before): (Option<&Landscape>, &HashMap<u32, LandscapeTexture>),
+    (after, ltex_after): (Option<&Landscape>, &HashMap<u32, LandscapeTexture>),
+) {
+    let empty = Landscape::default();
+    let before = before.unwrap_or(&empty);
+    let after = after.unwrap_or(&empty);
+
+    // heights
+    let flat = [[0.0; VERTEX_CNT]; VERTEX_CNT];
+    let heights_before = get_landscape_heights(before).unwrap_or(flat);
+    let heights_after = get_landscape_heights(after).unwrap_or(flat);
+    for (row_before, row_after) in heights_before.iter().zip(heights_after.iter()) {
+        for (a, b) in row_before.iter().zip(row_after.iter()) {
+            let delta = (a - b).abs();
+            if delta > 0.0 {
+                diff.heights_changed += 1;
+                diff.max_height_delta = diff.max_height_delta.max(delta);
+            }
+        }
+    }
+
+    // vertex colors
+    let colors = |land: &Landscape| {
+        land.landscape_flags
+            .contains(LandscapeFlags::USES_VERTEX_COLORS)
+            .then(|| land.vertex_colors.data.clone())
+    };
+    let colors_before = colors(before);
+    let colors_after = colors(after);
+    if colors_before != colors_after {
+        let white = [[[255; 3]; VERTEX_CNT]; VERTEX_CNT];
+        let a = colors_before.as_deref().unwrap_or(&white);
+        let b = colors_after.as_deref().unwrap_or(&white);
+        diff.vertex_colors_changed = a
+            .iter()
+            .flatten()
+            .zip(b.iter().flatten())
+            .filter(|(a, b)| a != b)
+            .count();
+    }
+
+    // textures, compared by file name since indices depend on the plugin's LTEX records
+    let textures = |land: &Landscape, ltex_records: &HashMap<u32, LandscapeTexture>| {
+        let mut names = vec![String::new(); GRID_SIZE * GRID_SIZE];
+        if land.landscape_flags.contains(LandscapeFlags::USES_TEXTURES) {
+            for (name, index) in names
+                .iter_mut()
+                .zip(land.texture_indices.data.iter().flatten())
+            {
+                if let Some(ltex) = ltex_records.get(&(*index as u32)) {
+                    *name = ltex.file_name.to_lowercase();
+                }
+            }
+        }
+        names
+    };
+    diff.textures_changed = textures(before, ltex_before)
+        .iter()
+        .zip(textures(after, ltex_after).iter())
+        .filter(|(a, b)| a != b)
+        .count();
+}
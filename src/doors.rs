@@ -0,0 +1,149 @@
+use std::collections::{hash_map::Entry, BTreeSet};
+
+use tes3::esp::Cell;
+
+use crate::interior::get_placed_references;
+use crate::*;
+
+/// An exterior door that leads into an interior
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoorEntrance {
+    pub cell: CellKey,
+    pub translation: [f32; 3],
+    /// lowercase name of the interior
+    pub interior: String,
+}
+
+/// A way through interiors that comes out in another exterior cell than it was entered from,
+/// e.g. a cave with two entrances or a door that teleports
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoorConnection {
+    /// the exterior door the interior is entered from
+    pub from: [f32; 3],
+    /// the exterior position it comes out at
+    pub to: [f32; 3],
+    /// the interior with the door
+    pub interior: String,
+    /// the interior or exterior the door leads to
+    pub destination: String,
+}
+
+impl DoorConnection {
+    pub fn from_cell(&self) -> CellKey {
+        get_cell_at_translation(self.from)
+    }
+
+    pub fn to_cell(&self) -> CellKey {
+        get_cell_at_translation(self.to)
+    }
+}
+
+/// Exterior door positions every interior can be reached from, by lowercase interior name
+pub type InteriorHomes = HashMap<String, Vec<[f32; 3]>>;
+
+/// Find all exterior doors into interiors, then follow the doors inside the interiors
+/// to the exterior cells they come out in
+pub fn find_doors(
+    cell_records: &HashMap<CellKey, Cell>,
+    interior_records: &HashMap<String, Cell>,
+) -> (Vec<DoorEntrance>, InteriorHomes, Vec<DoorConnection>) {
+    // exterior doors
+    let mut keys = cell_records.keys().copied().collect::<Vec<_>>();
+    keys.sort();
+    let mut entrances = vec![];
+    for key in keys {
+        for reference in get_placed_references(&cell_records[&key]) {
+            if let Some(destination) = &reference.door_destination_cell {
+                entrances.push(DoorEntrance {
+                    cell: key,
+                    translation: reference.translation,
+                    interior: destination.to_lowercase(),
+                });
+            }
+        }
+    }
+
+    // the exterior doors every interior can be reached from, also through other interiors
+    let mut homes: InteriorHomes = HashMap::default();
+    for entrance in &entrances {
+        homes
+            .entry(entrance.interior.clone())
+            .or_default()
+            .push(entrance.translation);
+    }
+    let mut ids = interior_records.keys().collect::<Vec<_>>();
+    ids.sort();
+    loop {
+        let mut changed = false;
+        for id in &ids {
+            let Some(home) = homes.get(*id).cloned() else {
+                continue;
+            };
+            for reference in get_placed_references(&interior_records[*id]) {
+                let Some(destination) = &reference.door_destination_cell else {
+                    continue;
+                };
+                if let Entry::Vacant(e) = homes.entry(destination.to_lowercase()) {
+                    e.insert(home.clone());
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // doors that come out in another exterior cell than the interior is entered from
+    let mut connections = vec![];
+    let mut seen = BTreeSet::new();
+    for id in ids {
+        let Some(home) = homes.get(id) else {
+            continue;
+        };
+        let home_cells = home
+            .iter()
+            .map(|t| get_cell_at_translation(*t))
+            .collect::<Vec<_>>();
+
+        for reference in get_placed_references(&interior_records[id]) {
+            let (to, destination) = match (
+                &reference.door_destination_cell,
+                reference.door_destination_coords,
+            ) {
+                // to another interior, out through its entrance
+                (Some(cell), _) => {
+                    let Some(to) = homes.get(&cell.to_lowercase()).and_then(|h| h.first()) else {
+                        continue;
+                    };
+                    (*to, cell.clone())
+                }
+                // to the exterior
+                (None, Some(coords)) => {
+                    let to = [coords[0], coords[1], coords[2]];
+                    let (x, y) = get_cell_at_translation(to);
+                    (to, format!("({}, {})", x, y))
+                }
+                (None, None) => continue,
+            };
+
+            let to_cell = get_cell_at_translation(to);
+            if home_cells.contains(&to_cell) {
+                continue;
+            }
+
+            let from = home[0];
+            let from_cell = get_cell_at_translation(from);
+            if seen.insert((from_cell, to_cell, id.clone())) {
+                connections.push(DoorConnection {
+                    from,
+                    to,
+                    interior: id.clone(),
+                    destination,
+                });
+            }
+        }
+    }
+
+    (entrances, homes, connections)
+}
@@ -17,6 +17,9 @@ impl eframe::App for TemplateApp {
             }
         }
 
+        // apply finished background loads
+        self.poll_loader(ctx);
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
             egui::menu::bar(ui, |ui| {
@@ -51,12 +54,28 @@ impl eframe::App for TemplateApp {
                     "Plugins",
                 );
                 ui.selectable_value(&mut self.side_panel_view, ESidePanelView::Cells, "Cells");
+                ui.selectable_value(
+                    &mut self.side_panel_view,
+                    ESidePanelView::Conflicts,
+                    "Conflicts",
+                );
+                ui.selectable_value(
+                    &mut self.side_panel_view,
+                    ESidePanelView::Interiors,
+                    "Interiors",
+                );
+                ui.selectable_value(&mut self.side_panel_view, ESidePanelView::Regions, "Regions");
+                ui.selectable_value(&mut self.side_panel_view, ESidePanelView::Route, "Route");
             });
 
             match self.side_panel_view {
                 // view
                 app::ESidePanelView::Plugins => self.plugins_panel(ui, ctx),
                 app::ESidePanelView::Cells => self.cell_panel(ui, ctx),
+                app::ESidePanelView::Conflicts => self.conflicts_panel(ui),
+                app::ESidePanelView::Interiors => self.interiors_panel(ui),
+                app::ESidePanelView::Regions => self.regions_panel(ui),
+                app::ESidePanelView::Route => self.route_panel(ui),
             }
         });
 
@@ -67,14 +86,14 @@ impl eframe::App for TemplateApp {
                 // map bounds
                 ui.label(format!(
                     "({},{}) - ({},{})",
-                    self.dimensions.min_x,
-                    self.dimensions.min_y,
-                    self.dimensions.max_x,
-                    self.dimensions.max_y
+                    self.world.dimensions.min_x,
+                    self.world.dimensions.min_y,
+                    self.world.dimensions.max_x,
+                    self.world.dimensions.max_y
                 ));
                 ui.separator();
                 ui.label(get_cell_name(
-                    &self.cell_records,
+                    &self.world.cell_records,
                     self.runtime_data.hover_pos,
                 ));
 
@@ -0,0 +1,207 @@
+use std::{fs, path::Path};
+
+use image::{ImageBuffer, ImageError, Luma};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// File formats of a heightmap export, picked by the file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EHeightmapFormat {
+    /// 16 bit grayscale png, min_z to max_z mapped to 1 to 65535, 0 is no data
+    Png16,
+    /// raw little endian unsigned 16 bit without header, mapped like the png
+    R16,
+    /// raw little endian f32 in game units without header
+    RawF32,
+}
+
+impl EHeightmapFormat {
+    pub const ALL: [EHeightmapFormat; 3] = [
+        EHeightmapFormat::Png16,
+        EHeightmapFormat::R16,
+        EHeightmapFormat::RawF32,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            EHeightmapFormat::Png16 => "png",
+            EHeightmapFormat::R16 => "r16",
+            EHeightmapFormat::RawF32 => "raw",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EHeightmapFormat::Png16 => "16 bit png",
+            EHeightmapFormat::R16 => "raw 16 bit",
+            EHeightmapFormat::RawF32 => "raw 32 bit float",
+        }
+    }
+
+    /// Type of a value in the file
+    pub fn sample_format(&self) -> &'static str {
+        match self {
+            EHeightmapFormat::Png16 | EHeightmapFormat::R16 => "u16",
+            EHeightmapFormat::RawF32 => "f32",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        Self::ALL.into_iter().find(|f| f.extension() == extension)
+    }
+}
+
+/// Contents of the json sidecar next to an exported heightmap.
+/// Every cell is 65 x 65 vertices, the border vertices are shared with the neighbor cells
+/// and appear twice. Rows go from north to south, the first vertex is the north-west corner
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HeightmapMetadata {
+    pub format: EHeightmapFormat,
+    /// "u16" for unsigned 16 bit values, "f32" for floats in game units
+    #[serde(default)]
+    pub sample_format: String,
+    /// size in vertices
+    pub width: u32,
+    pub height: u32,
+    pub vertices_per_cell: u32,
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+    /// heights in game units of the lowest and highest value, 1 and 65535 in the 16 bit formats
+    pub min_z: f32,
+    pub max_z: f32,
+    /// game units between two vertices of a cell
+    pub vertex_spacing: f32,
+    /// world position of the first vertex, the north-west corner of cell (min_x, max_y)
+    pub origin: [f32; 2],
+    /// value of vertices in cells without landscape, 0 in the 16 bit formats, below min_z as float
+    pub no_data: f32,
+}
+
+impl HeightmapMetadata {
+    pub fn new(dimensions: &Dimensions, format: EHeightmapFormat) -> Self {
+        Self {
+            format,
+            sample_format: format.sample_format().to_owned(),
+            width: dimensions.stride(VERTEX_CNT) as u32,
+            height: (dimensions.height() * VERTEX_CNT) as u32,
+            vertices_per_cell: VERTEX_CNT as u32,
+            min_x: dimensions.min_x,
+            min_y: dimensions.min_y,
+            max_x: dimensions.max_x,
+            max_y: dimensions.max_y,
+            min_z: dimensions.min_z,
+            max_z: dimensions.max_z,
+            vertex_spacing: CELL_SIZE / (VERTEX_CNT - 1) as f32,
+            origin: [
+                dimensions.min_x as f32 * CELL_SIZE,
+                (dimensions.max_y + 1) as f32 * CELL_SIZE,
+            ],
+            no_data: match format {
+                EHeightmapFormat::RawF32 => dimensions.min_z - 1.0,
+                _ => 0.0,
+            },
+        }
+    }
+
+    /// Map a height to 1 to 65535, cells without landscape are 0
+    pub fn to_u16(&self, z: f32) -> u16 {
+        if z < self.min_z {
+            return 0;
+        }
+        let range = (self.max_z - self.min_z).max(1.0);
+        1 + (((z - self.min_z) / range).min(1.0) * (u16::MAX - 1) as f32).round() as u16
+    }
+
+    /// Height of a 16 bit value, None for no data
+    pub fn from_u16(&self, v: u16) -> Option<f32> {
+        if v == 0 {
+            return None;
+        }
+        let range = (self.max_z - self.min_z).max(1.0);
+        Some(self.min_z + (v - 1) as f32 / (u16::MAX - 1) as f32 * range)
+    }
+}
+
+/// Write the decoded vertex heights of the world with a `.json` sidecar next to it
+pub fn export_heightmap(
+    world: &WorldData,
+    path: &Path,
+    format: EHeightmapFormat,
+) -> Result<HeightmapMetadata, ImageError> {
+    let metadata = HeightmapMetadata::new(&world.dimensions, format);
+    if world.heights.len() != (metadata.width * metadata.height) as usize {
+        return Err(ImageError::IoError(std::io::Error::other(
+            "Heights don't match the dimensions",
+        )));
+    }
+
+    match format {
+        EHeightmapFormat::Png16 => {
+            let pixels = world
+                .heights
+                .iter()
+                .map(|z| metadata.to_u16(*z))
+                .collect::<Vec<_>>();
+            let image: ImageBuffer<Luma<u16>, Vec<u16>> =
+                ImageBuffer::from_raw(metadata.width, metadata.height, pixels)
+                    .expect("buffer size matches the dimensions");
+            image.save(path)?;
+        }
+        EHeightmapFormat::R16 => {
+            let bytes = world
+                .heights
+                .iter()
+                .flat_map(|z| metadata.to_u16(*z).to_le_bytes())
+                .collect::<Vec<_>>();
+            fs::write(path, bytes)?;
+        }
+        EHeightmapFormat::RawF32 => {
+            let bytes = world
+                .heights
+                .iter()
+                .flat_map(|z| z.to_le_bytes())
+                .collect::<Vec<_>>();
+            fs::write(path, bytes)?;
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&metadata)
+        .map_err(|e| ImageError::IoError(std::io::Error::other(e)))?;
+    fs::write(path.with_extension("json"), json)?;
+
+    info!(
+        "Exported {} x {} heightmap to {}",
+        metadata.width,
+        metadata.height,
+        path.display()
+    );
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_data_is_not_the_lowest_height() {
+        let dimensions = Dimensions {
+            min_z: -2048.0,
+            max_z: 4096.0,
+            ..Default::default()
+        };
+        let metadata = HeightmapMetadata::new(&dimensions, EHeightmapFormat::R16);
+        assert_eq!(metadata.sample_format, "u16");
+
+        assert_eq!(metadata.to_u16(-2049.0), 0);
+        assert_eq!(metadata.to_u16(-2048.0), 1);
+        assert_eq!(metadata.to_u16(4096.0), u16::MAX);
+        assert_eq!(metadata.from_u16(0), None);
+        assert_eq!(metadata.from_u16(1), Some(-2048.0));
+        assert_eq!(metadata.from_u16(u16::MAX), Some(4096.0));
+    }
+}
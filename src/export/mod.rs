@@ -0,0 +1,8 @@
+pub mod heightmap;
+pub mod raster;
+pub mod svg;
+pub mod xyz;
+
+pub use heightmap::{export_heightmap, EHeightmapFormat, HeightmapMetadata};
+pub use svg::compose_svg;
+pub use xyz::{export_xyz_tiles, XyzMetadata};
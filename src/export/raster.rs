@@ -0,0 +1,154 @@
+use std::sync::OnceLock;
+
+use egui::{
+    epaint::{Fonts, Mesh, TessellationOptions, Tessellator, Vertex},
+    pos2, vec2, FontDefinitions, Pos2, Rect, Shape,
+};
+use image::{Rgba, RgbaImage};
+
+/// Width of the font atlas of exports
+const FONT_TEXTURE_SIDE: usize = 2048;
+
+/// Fonts for the text of exported images and svgs, at one pixel per point.
+/// Text shapes drawn with draw_shapes have to be laid out with these
+pub fn export_fonts() -> &'static Fonts {
+    static FONTS: OnceLock<Fonts> = OnceLock::new();
+    FONTS.get_or_init(|| Fonts::new(1.0, FONT_TEXTURE_SIDE, FontDefinitions::default()))
+}
+
+/// Coverage of the font atlas, the top left pixel is white for all shapes that are not text
+struct FontTexture {
+    size: [usize; 2],
+    alpha: Vec<u8>,
+}
+
+impl FontTexture {
+    fn new(fonts: &Fonts) -> Self {
+        let image = fonts.image();
+        Self {
+            size: image.size,
+            alpha: image.srgba_pixels(None).map(|c| c.a()).collect(),
+        }
+    }
+
+    fn sample(&self, uv: Pos2) -> f32 {
+        let x = ((uv.x * self.size[0] as f32) as usize).min(self.size[0] - 1);
+        let y = ((uv.y * self.size[1] as f32) as usize).min(self.size[1] - 1);
+        self.alpha[y * self.size[0] + x] as f32 / 255.0
+    }
+}
+
+/// Rasterize shapes onto an image.
+/// Shapes are tessellated the same way egui does for the screen, so strokes have the same width
+/// and edges are anti-aliased by the tessellator's feathering. Text is sampled from the
+/// font atlas of export_fonts
+pub fn draw_shapes(image: &mut RgbaImage, shapes: Vec<Shape>) {
+    let fonts = export_fonts();
+    let texture = FontTexture::new(fonts);
+
+    let size = vec2(image.width() as f32, image.height() as f32);
+    let mut tessellator =
+        Tessellator::new(1.0, TessellationOptions::default(), texture.size, vec![]);
+    tessellator.set_clip_rect(Rect::from_min_size(Pos2::ZERO, size));
+
+    for shape in shapes {
+        // callbacks need a gpu
+        if matches!(shape, Shape::Callback(_)) {
+            continue;
+        }
+
+        let mut mesh = Mesh::default();
+        tessellator.tessellate_shape(shape, &mut mesh);
+        draw_mesh(image, &mesh, &texture);
+    }
+}
+
+fn draw_mesh(image: &mut RgbaImage, mesh: &Mesh, texture: &FontTexture) {
+    for triangle in mesh.indices.chunks_exact(3) {
+        let a = &mesh.vertices[triangle[0] as usize];
+        let b = &mesh.vertices[triangle[1] as usize];
+        let c = &mesh.vertices[triangle[2] as usize];
+        draw_triangle(image, a, b, c, texture);
+    }
+}
+
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Pixels exactly on a top or left edge belong to the triangle, so shared edges are drawn once
+fn is_top_left(a: Pos2, b: Pos2) -> bool {
+    let d = b - a;
+    (d.y == 0.0 && d.x > 0.0) || d.y < 0.0
+}
+
+fn draw_triangle(image: &mut RgbaImage, a: &Vertex, b: &Vertex, c: &Vertex, texture: &FontTexture) {
+    let (mut b, mut c) = (b, c);
+    let mut area = edge(a.pos, b.pos, c.pos);
+    if area == 0.0 {
+        return;
+    }
+    if area < 0.0 {
+        std::mem::swap(&mut b, &mut c);
+        area = -area;
+    }
+
+    let min_x = a.pos.x.min(b.pos.x).min(c.pos.x).floor().max(0.0) as u32;
+    let min_y = a.pos.y.min(b.pos.y).min(c.pos.y).floor().max(0.0) as u32;
+    let max_x = (a.pos.x.max(b.pos.x).max(c.pos.x).ceil().max(0.0) as u32).min(image.width());
+    let max_y = (a.pos.y.max(b.pos.y).max(c.pos.y).ceil().max(0.0) as u32).min(image.height());
+
+    let inside = |w: f32, from: Pos2, to: Pos2| w > 0.0 || (w == 0.0 && is_top_left(from, to));
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            // sample at the pixel center
+            let p = pos2(x as f32 + 0.5, y as f32 + 0.5);
+            let wa = edge(b.pos, c.pos, p);
+            let wb = edge(c.pos, a.pos, p);
+            let wc = edge(a.pos, b.pos, p);
+            if !inside(wa, b.pos, c.pos) || !inside(wb, c.pos, a.pos) || !inside(wc, a.pos, b.pos) {
+                continue;
+            }
+
+            // interpolate the premultiplied vertex colors, times the glyph coverage
+            let (wa, wb, wc) = (wa / area, wb / area, wc / area);
+            let uv = pos2(
+                wa * a.uv.x + wb * b.uv.x + wc * c.uv.x,
+                wa * a.uv.y + wb * b.uv.y + wc * c.uv.y,
+            );
+            let coverage = texture.sample(uv);
+            let mut color = [0.0; 4];
+            for (i, channel) in color.iter_mut().enumerate() {
+                *channel =
+                    (wa * a.color[i] as f32 + wb * b.color[i] as f32 + wc * c.color[i] as f32)
+                        / 255.0
+                        * coverage;
+            }
+
+            blend_pixel(image.get_pixel_mut(x, y), color);
+        }
+    }
+}
+
+/// Blend a premultiplied color over a pixel with straight alpha
+fn blend_pixel(pixel: &mut Rgba<u8>, src: [f32; 4]) {
+    let src_a = src[3].clamp(0.0, 1.0);
+    if src_a <= 0.0 && src[..3].iter().all(|c| *c <= 0.0) {
+        return;
+    }
+
+    let dst_a = pixel[3] as f32 / 255.0;
+    let out_a = src_a + dst_a * (1.0 - src_a);
+    if out_a <= 0.0 {
+        *pixel = Rgba([0, 0, 0, 0]);
+        return;
+    }
+
+    for i in 0..3 {
+        let dst = pixel[i] as f32 / 255.0 * dst_a;
+        let out = (src[i] + dst * (1.0 - src_a)) / out_a;
+        pixel[i] = (out.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    pixel[3] = (out_a * 255.0).round() as u8;
+}
@@ -0,0 +1,288 @@
+use std::{fmt::Write, io::Cursor};
+
+use base64::Engine;
+use egui::{
+    epaint::{ColorMode, PathStroke},
+    Color32, ColorImage, Pos2, Shape, Stroke,
+};
+use image::{ImageError, ImageFormat, RgbaImage};
+
+use crate::export::raster::export_fonts;
+use crate::overlay::paths::get_overlay_path_image;
+use crate::*;
+
+/// Build an svg of the map.
+/// The background and the paths overlay are embedded as png images, every other overlay is a
+/// layer of vector shapes and every cell has an invisible rect with its name and grid as title
+pub fn compose_svg(
+    world: &WorldData,
+    settings: &SavedData,
+    texture_map: &HashMap<String, ImageBuffer>,
+    intervention_engine: &str,
+) -> Result<String, ImageError> {
+    let background = world.get_background_image(settings.background, settings, texture_map);
+    let [width, height] = match &background {
+        Some(image) => image.size,
+        None => world.dimensions.pixel_size_tuple(VERTEX_CNT),
+    };
+    let transform = world.get_image_transform(width as u32, height as u32);
+
+    let mut svg = begin_svg(width, height);
+
+    if let Some(image) = &background {
+        begin_layer(&mut svg, "background", "Background");
+        write_image(&mut svg, image, width, height)?;
+        end_layer(&mut svg);
+    }
+
+    for overlay in settings.get_overlays() {
+        begin_layer(&mut svg, overlay.id(), overlay.label());
+        if overlay == EOverlay::Paths {
+            let image = get_overlay_path_image(&world.dimensions, &world.land_records);
+            write_image(&mut svg, &image, width, height)?;
+        } else {
+            for shape in world.get_overlay_shapes(
+                overlay,
+                transform,
+                settings,
+                intervention_engine,
+                export_fonts(),
+            ) {
+                write_shape(&mut svg, &shape);
+            }
+        }
+        end_layer(&mut svg);
+    }
+
+    // searchable cell names
+    begin_layer(&mut svg, "cells", "Cells");
+    let d = &world.dimensions;
+    for cy in d.min_y..=d.max_y {
+        for cx in d.min_x..=d.max_x {
+            let key = (cx, cy);
+            let Some(cell) = world.cell_records.get(&key) else {
+                continue;
+            };
+
+            let rect = get_rect_at_cell(d, transform, key);
+            let title = if cell.name.is_empty() {
+                format!("({}, {})", cx, cy)
+            } else {
+                format!("{} ({}, {})", cell.name, cx, cy)
+            };
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" pointer-events="all"><title>{}</title></rect>"#,
+                rect.min.x,
+                rect.min.y,
+                rect.width(),
+                rect.height(),
+                escape(&title)
+            );
+        }
+    }
+    end_layer(&mut svg);
+
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+/// Plan of an interior with the given longest side, one layer per object type
+pub fn compose_interior_svg(world: &WorldData, interior: &str, size: u32) -> String {
+    let (transform, [width, height]) = world.get_interior_transform(interior, size);
+
+    let mut svg = begin_svg(width as usize, height as usize);
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="rgb(30,30,30)"/>"#,
+        width, height
+    );
+    for (object_type, shapes) in world.get_interior_layers(interior, transform) {
+        begin_layer(&mut svg, object_type.id(), object_type.label());
+        for shape in shapes {
+            write_shape(&mut svg, &shape);
+        }
+        end_layer(&mut svg);
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn begin_svg(width: usize, height: usize) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    );
+    svg
+}
+
+fn begin_layer(svg: &mut String, id: &str, label: &str) {
+    let _ = writeln!(
+        svg,
+        r#"<g id="{}" inkscape:groupmode="layer" inkscape:label="{}">"#,
+        id,
+        escape(label)
+    );
+}
+
+fn end_layer(svg: &mut String) {
+    svg.push_str("</g>\n");
+}
+
+fn write_image(
+    svg: &mut String,
+    image: &ColorImage,
+    width: usize,
+    height: usize,
+) -> Result<(), ImageError> {
+    let image: RgbaImage = color_image_to_rgba_image(image)?;
+    let mut png = vec![];
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+
+    let _ = writeln!(
+        svg,
+        r#"<image x="0" y="0" width="{}" height="{}" preserveAspectRatio="none" href="data:image/png;base64,{}"/>"#,
+        width,
+        height,
+        base64::engine::general_purpose::STANDARD.encode(png)
+    );
+    Ok(())
+}
+
+fn write_shape(svg: &mut String, shape: &Shape) {
+    match shape {
+        Shape::Vec(shapes) => {
+            for shape in shapes {
+                write_shape(svg, shape);
+            }
+        }
+        Shape::Rect(rect) => {
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" {} {}/>"#,
+                rect.rect.min.x,
+                rect.rect.min.y,
+                rect.rect.width(),
+                rect.rect.height(),
+                rect.rounding.nw,
+                fill(rect.fill),
+                stroke(rect.stroke)
+            );
+        }
+        Shape::Circle(circle) => {
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}" {} {}/>"#,
+                circle.center.x,
+                circle.center.y,
+                circle.radius,
+                fill(circle.fill),
+                stroke(circle.stroke)
+            );
+        }
+        Shape::Ellipse(ellipse) => {
+            let _ = writeln!(
+                svg,
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {} {}/>"#,
+                ellipse.center.x,
+                ellipse.center.y,
+                ellipse.radius.x,
+                ellipse.radius.y,
+                fill(ellipse.fill),
+                stroke(ellipse.stroke)
+            );
+        }
+        Shape::LineSegment { points, stroke } => {
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" fill="none" {}/>"#,
+                points[0].x,
+                points[0].y,
+                points[1].x,
+                points[1].y,
+                path_stroke(stroke)
+            );
+        }
+        Shape::Path(path) => {
+            let element = if path.closed { "polygon" } else { "polyline" };
+            let _ = writeln!(
+                svg,
+                r#"<{} points="{}" {} {}/>"#,
+                element,
+                points(&path.points),
+                fill(path.fill),
+                path_stroke(&path.stroke)
+            );
+        }
+        Shape::Text(text) => {
+            let galley = &text.galley;
+            let format = galley.job.sections.first().map(|s| &s.format);
+            let size = format.map(|f| f.font_id.size).unwrap_or(14.0);
+            let color = text
+                .override_text_color
+                .or(format.map(|f| f.color))
+                .unwrap_or(text.fallback_color);
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="{}" font-family="sans-serif" dominant-baseline="hanging" {}>{}</text>"#,
+                text.pos.x,
+                text.pos.y,
+                size,
+                fill(color),
+                escape(galley.text())
+            );
+        }
+        _ => {}
+    }
+}
+
+fn points(points: &[Pos2]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn color(color: Color32) -> (String, f32) {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    (format!("rgb({},{},{})", r, g, b), a as f32 / 255.0)
+}
+
+fn fill(fill: Color32) -> String {
+    if fill == Color32::TRANSPARENT {
+        return r#"fill="none""#.to_owned();
+    }
+    let (rgb, opacity) = color(fill);
+    format!(r#"fill="{}" fill-opacity="{}""#, rgb, opacity)
+}
+
+fn stroke(stroke: Stroke) -> String {
+    if stroke.is_empty() {
+        return String::new();
+    }
+    let (rgb, opacity) = color(stroke.color);
+    format!(
+        r#"stroke="{}" stroke-opacity="{}" stroke-width="{}""#,
+        rgb, opacity, stroke.width
+    )
+}
+
+fn path_stroke(path_stroke: &PathStroke) -> String {
+    match path_stroke.color {
+        ColorMode::Solid(color) => stroke(Stroke::new(path_stroke.width, color)),
+        // gradients are not used by any overlay
+        ColorMode::UV(_) => String::new(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,158 @@
+use std::{fs, path::Path};
+
+use image::{imageops, ImageError, RgbaImage};
+use log::info;
+use serde::Serialize;
+
+use crate::*;
+
+/// Default tile size of leaflet and most other web map libraries
+pub const TILE_SIZE: u32 = 256;
+
+/// Contents of metadata.json next to the tile pyramid
+#[derive(Debug, Clone, Serialize)]
+pub struct XyzMetadata {
+    pub tile_size: u32,
+    pub min_zoom: u32,
+    pub max_zoom: u32,
+    /// size of the full resolution image at max_zoom
+    pub width: u32,
+    pub height: u32,
+    pub pixels_per_cell: f32,
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+    pub cells: Vec<XyzCell>,
+}
+
+/// Where a cell ends up in the pyramid, in pixels and tiles at max_zoom
+#[derive(Debug, Clone, Serialize)]
+pub struct XyzCell {
+    pub grid: CellKey,
+    pub name: String,
+    pub region: String,
+    /// min x, min y, max x, max y
+    pub pixel_bounds: [u32; 4],
+    /// x, y of all tiles the cell touches
+    pub tiles: Vec<(u32, u32)>,
+}
+
+/// Cut a composed map image into a {z}/{x}/{y}.png tile pyramid and write metadata.json.
+/// The image is anchored top left, max_zoom is the first level where it fits without scaling
+pub fn export_xyz_tiles(
+    world: &WorldData,
+    image: &RgbaImage,
+    dir: &Path,
+    tile_size: u32,
+) -> Result<XyzMetadata, ImageError> {
+    let tile_size = tile_size.max(1);
+    let side = image.width().max(image.height());
+    let mut max_zoom = 0;
+    while (tile_size << max_zoom) < side {
+        max_zoom += 1;
+    }
+
+    // write levels from the full resolution down, halving each time
+    let mut level = image.clone();
+    for z in (0..=max_zoom).rev() {
+        write_level(&level, &dir.join(z.to_string()), tile_size)?;
+        if z > 0 {
+            level = imageops::resize(
+                &level,
+                level.width().div_ceil(2).max(1),
+                level.height().div_ceil(2).max(1),
+                imageops::FilterType::Triangle,
+            );
+        }
+    }
+
+    let metadata = get_metadata(world, image.width(), image.height(), tile_size, max_zoom);
+    let json = serde_json::to_string_pretty(&metadata)
+        .map_err(|e| ImageError::IoError(std::io::Error::other(e)))?;
+    fs::write(dir.join("metadata.json"), json)?;
+
+    info!(
+        "Exported {} zoom levels of {} px tiles to {}",
+        max_zoom + 1,
+        tile_size,
+        dir.display()
+    );
+    Ok(metadata)
+}
+
+fn write_level(level: &RgbaImage, dir: &Path, tile_size: u32) -> Result<(), ImageError> {
+    let columns = level.width().div_ceil(tile_size);
+    let rows = level.height().div_ceil(tile_size);
+    for x in 0..columns {
+        let column_dir = dir.join(x.to_string());
+        fs::create_dir_all(&column_dir)?;
+        for y in 0..rows {
+            // tiles on the right and bottom edge are padded with transparency
+            let mut tile = RgbaImage::new(tile_size, tile_size);
+            let view =
+                imageops::crop_imm(level, x * tile_size, y * tile_size, tile_size, tile_size);
+            imageops::replace(&mut tile, &*view, 0, 0);
+            tile.save(column_dir.join(format!("{}.png", y)))?;
+        }
+    }
+    Ok(())
+}
+
+fn get_metadata(
+    world: &WorldData,
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    max_zoom: u32,
+) -> XyzMetadata {
+    let d = &world.dimensions;
+    let transform = world.get_image_transform(width, height);
+
+    let mut cells = vec![];
+    for cy in d.min_y..=d.max_y {
+        for cx in d.min_x..=d.max_x {
+            let key = (cx, cy);
+            let cell = world.cell_records.get(&key);
+            if cell.is_none() && !world.land_records.contains_key(&key) {
+                continue;
+            }
+
+            let rect = get_rect_at_cell(d, transform, key);
+            let pixel_bounds = [
+                rect.min.x.round() as u32,
+                rect.min.y.round() as u32,
+                rect.max.x.round() as u32,
+                rect.max.y.round() as u32,
+            ];
+            let mut tiles = vec![];
+            for tx in pixel_bounds[0] / tile_size..=(pixel_bounds[2].max(1) - 1) / tile_size {
+                for ty in pixel_bounds[1] / tile_size..=(pixel_bounds[3].max(1) - 1) / tile_size {
+                    tiles.push((tx, ty));
+                }
+            }
+
+            cells.push(XyzCell {
+                grid: key,
+                name: cell.map(|c| c.name.clone()).unwrap_or_default(),
+                region: cell.and_then(|c| c.region.clone()).unwrap_or_default(),
+                pixel_bounds,
+                tiles,
+            });
+        }
+    }
+
+    XyzMetadata {
+        tile_size,
+        min_zoom: 0,
+        max_zoom,
+        width,
+        height,
+        pixels_per_cell: width as f32 / d.width().max(1) as f32,
+        min_x: d.min_x,
+        min_y: d.min_y,
+        max_x: d.max_x,
+        max_y: d.max_y,
+        cells,
+    }
+}
@@ -0,0 +1,563 @@
+use std::{fs, path::Path};
+
+use image::ImageError;
+use log::info;
+use tes3::esp::{
+    FixedString, Header, Landscape, Plugin, TES3Object, VertexHeights, VertexNormals, WorldMapData,
+};
+
+use crate::export::{EHeightmapFormat, HeightmapMetadata};
+use crate::*;
+
+/// Vertices per cell side that are not shared with the next cell
+const CELL_VERTICES: usize = VERTEX_CNT - 1;
+/// Largest height difference between two neighboring vertices a LAND record can store
+const MAX_STEP: f32 = i8::MAX as f32 * 8.0;
+/// Passes of limit_steps over the heightmap before the remaining steps are clamped per cell
+const MAX_STEP_PASSES: usize = 32;
+
+/// What an imported heightmap changed
+#[derive(Debug, Clone, Default)]
+pub struct HeightmapImport {
+    /// LAND records written to the plugin
+    pub cells: Vec<CellKey>,
+    /// vertices raised or lowered because they were too steep for a LAND record
+    pub clamped: usize,
+    /// plugins whose LAND records are replaced, in load order
+    pub masters: Vec<String>,
+}
+
+/// Read an exported heightmap, or one edited in another tool, with the `.json` sidecar next to it.
+/// Without a sidecar the heightmap has to match the loaded dimensions and height range.
+/// Returns the metadata and the heights in game units, rows from north to south
+pub fn read_heightmap(
+    path: &Path,
+    dimensions: &Dimensions,
+) -> Result<(HeightmapMetadata, Vec<Option<f32>>), ImageError> {
+    let format = EHeightmapFormat::from_path(path)
+        .ok_or_else(|| invalid_data(&format!("Unknown heightmap format: {}", path.display())))?;
+
+    let sidecar = path.with_extension("json");
+    let metadata = if sidecar.exists() {
+        let json = fs::read_to_string(&sidecar)?;
+        serde_json::from_str::<HeightmapMetadata>(&json)
+            .map_err(|e| ImageError::IoError(std::io::Error::other(e)))?
+    } else {
+        HeightmapMetadata::new(dimensions, format)
+    };
+
+    let size = (metadata.width * metadata.height) as usize;
+
+    let heights = match format {
+        EHeightmapFormat::Png16 => {
+            // 8 bit grayscale is stretched to 16 bit
+            let image = image::open(path)?.into_luma16();
+            if image.width() != metadata.width || image.height() != metadata.height {
+                return Err(invalid_data(&format!(
+                    "Heightmap is {} x {}, expected {} x {}",
+                    image.width(),
+                    image.height(),
+                    metadata.width,
+                    metadata.height
+                )));
+            }
+            image
+                .pixels()
+                .map(|p| metadata.from_u16(p[0]))
+                .collect::<Vec<_>>()
+        }
+        EHeightmapFormat::R16 => fs::read(path)?
+            .chunks_exact(2)
+            .map(|b| metadata.from_u16(u16::from_le_bytes([b[0], b[1]])))
+            .collect(),
+        EHeightmapFormat::RawF32 => fs::read(path)?
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .map(|z| (z > metadata.no_data).then_some(z))
+            .collect(),
+    };
+    if heights.len() != size {
+        return Err(invalid_data(&format!(
+            "Heightmap has {} values, expected {}",
+            heights.len(),
+            size
+        )));
+    }
+
+    Ok((metadata, heights))
+}
+
+/// Import a heightmap and write the LAND records of all cells whose heights changed into a new plugin
+pub fn import_heightmap(
+    world: &WorldData,
+    heightmap: &Path,
+    output: &Path,
+) -> Result<HeightmapImport, ImageError> {
+    let (metadata, heights) = read_heightmap(heightmap, &world.dimensions)?;
+    let (landscapes, clamped) = get_imported_landscapes(world, &metadata, &heights);
+
+    let cells = landscapes.iter().map(|l| l.grid).collect::<Vec<_>>();
+    let masters = world
+        .plugins
+        .iter()
+        .filter(|vm| {
+            cells.iter().any(|key| {
+                world
+                    .cell_edits
+                    .get(key)
+                    .is_some_and(|edits| edits.iter().any(|e| e.land && e.plugin == vm.hash))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut plugin = Plugin::new();
+    plugin.objects.push(TES3Object::Header(Header {
+        version: 1.3,
+        author: FixedString("tes3map".to_owned()),
+        description: FixedString(format!("Heights imported from {}", heightmap.display())),
+        num_objects: landscapes.len() as u32,
+        masters: masters
+            .iter()
+            .map(|vm| {
+                let size = fs::metadata(&vm.path).map(|m| m.len()).unwrap_or_default();
+                (vm.get_name(), size)
+            })
+            .collect(),
+        ..Default::default()
+    }));
+    plugin
+        .objects
+        .extend(landscapes.into_iter().map(TES3Object::Landscape));
+    plugin.save_path(output)?;
+
+    info!(
+        "Imported heights of {} cells into {}, {} vertices clamped",
+        cells.len(),
+        output.display(),
+        clamped
+    );
+    Ok(HeightmapImport {
+        cells,
+        clamped,
+        masters: masters.iter().map(|vm| vm.get_name()).collect(),
+    })
+}
+
+/// Copies of the loaded LAND records with the imported heights, sorted by cell.
+/// Border vertices appear in both cells of the heightmap and get the average of both, vertices
+/// shared with a landscape outside the heightmap keep its height. Vertices too steep for a LAND
+/// record are limited on the whole heightmap at once, so neighboring cells keep sharing their borders.
+/// Cells without a LAND record or without vertex heights are skipped, as are cells whose heights
+/// didn't change. Returns the records and the number of clamped vertices
+pub fn get_imported_landscapes(
+    world: &WorldData,
+    metadata: &HeightmapMetadata,
+    heights: &[Option<f32>],
+) -> (Vec<Landscape>, usize) {
+    let columns = (metadata.max_x - metadata.min_x + 1).max(0) as usize;
+    let rows = (metadata.max_y - metadata.min_y + 1).max(0) as usize;
+    let stride = metadata.width as usize;
+
+    // all vertices of the heightmap without duplicates, rows from south to north
+    let grid_width = columns * CELL_VERTICES + 1;
+    let grid_height = rows * CELL_VERTICES + 1;
+    let mut sums = vec![(0.0_f32, 0_u32); grid_width * grid_height];
+    let grid_index = |key: CellKey, x: i32, y: i32| -> Option<usize> {
+        let gx = (key.0 - metadata.min_x) * CELL_VERTICES as i32 + x;
+        let gy = (key.1 - metadata.min_y) * CELL_VERTICES as i32 + y;
+        if gx < 0 || gy < 0 || gx as usize >= grid_width || gy as usize >= grid_height {
+            return None;
+        }
+        Some(gy as usize * grid_width + gx as usize)
+    };
+
+    // without vertex heights a cell was exported as no data
+    let mut loaded: HashMap<CellKey, [[f32; 65]; 65]> = HashMap::default();
+    for cy in metadata.min_y..=metadata.max_y {
+        for cx in metadata.min_x..=metadata.max_x {
+            let key = (cx, cy);
+            let Some(before) = world.land_records.get(&key).and_then(get_landscape_heights) else {
+                continue;
+            };
+            loaded.insert(key, before);
+
+            for y in 0..VERTEX_CNT {
+                for x in 0..VERTEX_CNT {
+                    let tx = VERTEX_CNT * (cx - metadata.min_x) as usize + x;
+                    let ty = VERTEX_CNT * (metadata.max_y - cy) as usize + (CELL_VERTICES - y);
+                    let (Some(Some(z)), Some(i)) = (
+                        heights.get(ty * stride + tx),
+                        grid_index(key, x as i32, y as i32),
+                    ) else {
+                        continue;
+                    };
+                    sums[i].0 += z;
+                    sums[i].1 += 1;
+                }
+            }
+        }
+    }
+    let mut keys = loaded.keys().copied().collect::<Vec<_>>();
+    keys.sort();
+    let mut grid = sums
+        .iter()
+        .map(|(sum, count)| (*count > 0).then(|| sum / *count as f32))
+        .collect::<Vec<_>>();
+
+    // vertices without a value keep the loaded height
+    let mut pinned = vec![false; grid.len()];
+    for (key, before) in &loaded {
+        for (y, row) in before.iter().enumerate() {
+            for (x, z) in row.iter().enumerate() {
+                if let Some(i) = grid_index(*key, x as i32, y as i32) {
+                    if grid[i].is_none() {
+                        grid[i] = Some(*z);
+                        pinned[i] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // keep the borders with landscapes around the heightmap
+    let mut outside: HashMap<CellKey, [[f32; 65]; 65]> = HashMap::default();
+    for cy in metadata.min_y - 1..=metadata.max_y + 1 {
+        for cx in metadata.min_x - 1..=metadata.max_x + 1 {
+            let key = (cx, cy);
+            let inside = cx >= metadata.min_x
+                && cx <= metadata.max_x
+                && cy >= metadata.min_y
+                && cy <= metadata.max_y;
+            if inside {
+                continue;
+            }
+            let Some(heights) = world.land_records.get(&key).and_then(get_landscape_heights) else {
+                continue;
+            };
+            for (y, row) in heights.iter().enumerate() {
+                for (x, z) in row.iter().enumerate() {
+                    if let Some(i) = grid_index(key, x as i32, y as i32) {
+                        if grid[i].is_some() {
+                            grid[i] = Some(*z);
+                            pinned[i] = true;
+                        }
+                    }
+                }
+            }
+            outside.insert(key, heights);
+        }
+    }
+
+    // heights are stored in steps of 8 units
+    for z in grid.iter_mut().flatten() {
+        *z = (*z / 8.0).round() * 8.0;
+    }
+    let mut clamped = limit_steps(&mut grid, &pinned, grid_width);
+
+    // height of a vertex relative to a cell, beyond the heightmap from the landscape there
+    let height_at = |key: CellKey, x: i32, y: i32| -> Option<f32> {
+        if let Some(i) = grid_index(key, x, y) {
+            return grid[i];
+        }
+        let gx = (key.0 - metadata.min_x) * CELL_VERTICES as i32 + x;
+        let gy = (key.1 - metadata.min_y) * CELL_VERTICES as i32 + y;
+        let cell = (
+            metadata.min_x + gx.div_euclid(CELL_VERTICES as i32),
+            metadata.min_y + gy.div_euclid(CELL_VERTICES as i32),
+        );
+        outside.get(&cell).map(|heights| {
+            heights[gy.rem_euclid(CELL_VERTICES as i32) as usize]
+                [gx.rem_euclid(CELL_VERTICES as i32) as usize]
+        })
+    };
+
+    let mut landscapes = vec![];
+    for key in keys {
+        let land = &world.land_records[&key];
+        let before = &loaded[&key];
+
+        let mut cell_heights = [[0.0; VERTEX_CNT]; VERTEX_CNT];
+        let mut normals = [[[0; 3]; VERTEX_CNT]; VERTEX_CNT];
+        for y in 0..VERTEX_CNT {
+            for x in 0..VERTEX_CNT {
+                let (vx, vy) = (x as i32, y as i32);
+                let z = height_at(key, vx, vy).unwrap_or(before[y][x]);
+                cell_heights[y][x] = z;
+
+                // central differences, one sided next to missing landscape
+                let spacing = CELL_SIZE / CELL_VERTICES as f32;
+                let slope = |a: Option<f32>, b: Option<f32>| match (a, b) {
+                    (Some(a), Some(b)) => (b - a) / (2.0 * spacing),
+                    (None, Some(b)) => (b - z) / spacing,
+                    (Some(a), None) => (z - a) / spacing,
+                    (None, None) => 0.0,
+                };
+                let dx = slope(height_at(key, vx - 1, vy), height_at(key, vx + 1, vy));
+                let dy = slope(height_at(key, vx, vy - 1), height_at(key, vx, vy + 1));
+                normals[y][x] = get_vertex_normal(dx, dy);
+            }
+        }
+
+        let changed = before
+            .iter()
+            .flatten()
+            .zip(cell_heights.iter().flatten())
+            .any(|(a, b)| (a / 8.0).round() != (b / 8.0).round());
+        if !changed {
+            continue;
+        }
+
+        // only where limit_steps found no height between all neighbors
+        let (vertex_heights, cell_clamped) = encode_vertex_heights(&cell_heights);
+        clamped += cell_clamped;
+
+        let mut landscape = land.clone();
+        landscape.vertex_heights = vertex_heights;
+        landscape.vertex_normals = VertexNormals {
+            data: Box::new(normals),
+        };
+        landscape.world_map_data = WorldMapData {
+            data: Box::new(get_world_map_data(&cell_heights)),
+        };
+        landscapes.push(landscape);
+    }
+
+    (landscapes, clamped)
+}
+
+/// Raise or lower the vertices that are further than MAX_STEP from a neighbor, alternating the
+/// direction of the passes. Pinned vertices don't move. Returns the number of moved vertices
+fn limit_steps(grid: &mut [Option<f32>], pinned: &[bool], width: usize) -> usize {
+    let height = grid.len() / width.max(1);
+    let mut moved = vec![false; grid.len()];
+
+    for pass in 0..MAX_STEP_PASSES {
+        let mut changed = false;
+        for n in 0..grid.len() {
+            let i = if pass % 2 == 0 { n } else { grid.len() - 1 - n };
+            let Some(z) = grid[i] else {
+                continue;
+            };
+            if pinned[i] {
+                continue;
+            }
+
+            let (x, y) = (i % width, i / width);
+            let neighbors = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+            let (mut low, mut high) = (f32::MIN, f32::MAX);
+            for z in neighbors.into_iter().flatten().filter_map(|n| grid[n]) {
+                low = low.max(z - MAX_STEP);
+                high = high.min(z + MAX_STEP);
+            }
+            // no height is close enough to all neighbors
+            let limited = if low > high {
+                ((low + high) / 16.0).round() * 8.0
+            } else {
+                z.clamp(low, high)
+            };
+
+            if limited != z {
+                grid[i] = Some(limited);
+                moved[i] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    moved.iter().filter(|m| **m).count()
+}
+
+/// Encode heights in game units the way get_landscape_heights decodes them: an offset and
+/// byte deltas in steps of 8 units, along each row and down the first column.
+/// Deltas are taken against the already encoded heights so clamping doesn't add up.
+/// Returns the heights and the number of clamped deltas
+pub fn encode_vertex_heights(heights: &[[f32; 65]; 65]) -> (VertexHeights, usize) {
+    let mut data = [[0_i8; VERTEX_CNT]; VERTEX_CNT];
+    let mut clamped = 0;
+    let offset = (heights[0][0] / 8.0).round();
+
+    let encode = |previous: f32, z: f32| -> (f32, bool) {
+        let delta = (z / 8.0).round() - previous;
+        let value = delta.clamp(i8::MIN as f32, i8::MAX as f32);
+        (value, value != delta)
+    };
+
+    let mut row_start = offset;
+    for (y, row) in heights.iter().enumerate() {
+        let mut previous = row_start;
+        for (x, z) in row.iter().enumerate() {
+            if y == 0 && x == 0 {
+                continue;
+            }
+            let (delta, was_clamped) = encode(previous, *z);
+            if was_clamped {
+                clamped += 1;
+            }
+            data[y][x] = delta as i8;
+            previous += delta;
+            if x == 0 {
+                row_start = previous;
+            }
+        }
+    }
+
+    (
+        VertexHeights {
+            offset,
+            data: Box::new(data),
+        },
+        clamped,
+    )
+}
+
+/// Normal of a vertex from the height change per unit to the east and north
+fn get_vertex_normal(dx: f32, dy: f32) -> [i8; 3] {
+    let length = (dx * dx + dy * dy + 1.0).sqrt();
+    [
+        (-dx / length * 127.0).round() as i8,
+        (-dy / length * 127.0).round() as i8,
+        (1.0 / length * 127.0).round() as i8,
+    ]
+}
+
+fn invalid_data(msg: &str) -> ImageError {
+    ImageError::IoError(std::io::Error::new(std::io::ErrorKind::InvalidData, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use tes3::esp::LandscapeFlags;
+
+    use super::*;
+
+    fn landscape(grid: CellKey, heights: &[[f32; 65]; 65]) -> Landscape {
+        Landscape {
+            grid,
+            landscape_flags: LandscapeFlags::USES_VERTEX_HEIGHTS_AND_NORMALS,
+            vertex_heights: encode_vertex_heights(heights).0,
+            ..Default::default()
+        }
+    }
+
+    /// Two flat cells side by side, and the exported heights of both
+    fn two_cells(z: f32) -> (WorldData, HeightmapMetadata, Vec<Option<f32>>) {
+        let mut world = WorldData::default();
+        for key in [(0, 0), (1, 0)] {
+            world
+                .land_records
+                .insert(key, landscape(key, &[[z; VERTEX_CNT]; VERTEX_CNT]));
+        }
+        world.dimensions = Dimensions {
+            min_x: 0,
+            max_x: 1,
+            min_y: 0,
+            max_y: 0,
+            min_z: z,
+            max_z: z,
+        };
+        let metadata = HeightmapMetadata::new(&world.dimensions, EHeightmapFormat::RawF32);
+        let heights = vec![Some(z); (metadata.width * metadata.height) as usize];
+        (world, metadata, heights)
+    }
+
+    #[test]
+    fn vertex_heights_round_trip() {
+        let mut heights = [[0.0; VERTEX_CNT]; VERTEX_CNT];
+        for (y, row) in heights.iter_mut().enumerate() {
+            for (x, z) in row.iter_mut().enumerate() {
+                *z = ((x * 3 + y * 5) % 40) as f32 * 8.0 - 160.0;
+            }
+        }
+
+        let (vertex_heights, clamped) = encode_vertex_heights(&heights);
+        assert_eq!(clamped, 0);
+        let land = Landscape {
+            landscape_flags: LandscapeFlags::USES_VERTEX_HEIGHTS_AND_NORMALS,
+            vertex_heights,
+            ..Default::default()
+        };
+        assert_eq!(get_landscape_heights(&land), Some(heights));
+    }
+
+    #[test]
+    fn too_steep_steps_are_clamped() {
+        let mut heights = [[0.0; VERTEX_CNT]; VERTEX_CNT];
+        heights[10][10] = 4000.0;
+
+        let (vertex_heights, clamped) = encode_vertex_heights(&heights);
+        assert_eq!(clamped, 1);
+        let land = Landscape {
+            landscape_flags: LandscapeFlags::USES_VERTEX_HEIGHTS_AND_NORMALS,
+            vertex_heights,
+            ..Default::default()
+        };
+        let decoded = get_landscape_heights(&land).unwrap();
+        assert_eq!(decoded[10][10], MAX_STEP);
+        assert_eq!(decoded[10][11], 0.0);
+    }
+
+    #[test]
+    fn steep_edits_keep_cell_borders_shared() {
+        let (world, metadata, mut heights) = two_cells(0.0);
+        // the shared border in the copy of the western cell only, row 32
+        heights[32 * metadata.width as usize + 64] = Some(5000.0);
+
+        let (landscapes, clamped) = get_imported_landscapes(&world, &metadata, &heights);
+        assert_eq!(landscapes.len(), 2);
+        assert!(clamped > 0);
+        let west = get_landscape_heights(&landscapes[0]).unwrap();
+        let east = get_landscape_heights(&landscapes[1]).unwrap();
+        for y in 0..VERTEX_CNT {
+            assert_eq!(west[y][64], east[y][0]);
+        }
+        assert_eq!(west[32][64], MAX_STEP);
+    }
+
+    #[test]
+    fn edge_normals_use_the_landscape_outside() {
+        // rises by 16 units per vertex to the east border, the cell east of it is flat
+        let (mut world, _, _) = two_cells(1024.0);
+        let mut heights = [[0.0; VERTEX_CNT]; VERTEX_CNT];
+        for row in heights.iter_mut() {
+            for (x, z) in row.iter_mut().enumerate() {
+                *z = x as f32 * 16.0;
+            }
+        }
+        world
+            .land_records
+            .insert((0, 0), landscape((0, 0), &heights));
+        world.dimensions.max_x = 0;
+        let metadata = HeightmapMetadata::new(&world.dimensions, EHeightmapFormat::RawF32);
+        let mut imported = heights
+            .iter()
+            .rev()
+            .flatten()
+            .map(|z| Some(*z))
+            .collect::<Vec<_>>();
+        imported[0] = Some(800.0);
+
+        let (landscapes, _) = get_imported_landscapes(&world, &metadata, &imported);
+        let spacing = CELL_SIZE / CELL_VERTICES as f32;
+        assert_eq!(
+            landscapes[0].vertex_normals.data[32][64],
+            get_vertex_normal(8.0 / spacing, 0.0)
+        );
+    }
+
+    #[test]
+    fn cells_without_vertex_heights_are_skipped() {
+        let (mut world, metadata, heights) = two_cells(100.0);
+        world.land_records.get_mut(&(1, 0)).unwrap().landscape_flags = LandscapeFlags::empty();
+
+        let (landscapes, _) = get_imported_landscapes(&world, &metadata, &heights);
+        assert!(landscapes.is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+use egui::{
+    emath::{RectTransform, Rot2},
+    pos2, vec2, Color32, Pos2, Rect, Shape, Stroke, Vec2,
+};
+use tes3::esp::{Cell, Reference};
+
+use crate::*;
+
+/// Longest side of exported interior plans in pixels
+pub const INTERIOR_IMAGE_SIZE: u32 = 2048;
+
+/// Kinds of referenced objects, drawn with different markers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum EObjectType {
+    #[default]
+    Other,
+    Static,
+    Activator,
+    Container,
+    Light,
+    Door,
+    Creature,
+    Npc,
+}
+
+impl EObjectType {
+    /// All types in drawing order
+    pub const ALL: [EObjectType; 8] = [
+        EObjectType::Other,
+        EObjectType::Static,
+        EObjectType::Activator,
+        EObjectType::Container,
+        EObjectType::Light,
+        EObjectType::Door,
+        EObjectType::Creature,
+        EObjectType::Npc,
+    ];
+
+    /// Short name, used as svg layer id
+    pub fn id(&self) -> &'static str {
+        match self {
+            EObjectType::Other => "other",
+            EObjectType::Static => "statics",
+            EObjectType::Activator => "activators",
+            EObjectType::Container => "containers",
+            EObjectType::Light => "lights",
+            EObjectType::Door => "doors",
+            EObjectType::Creature => "creatures",
+            EObjectType::Npc => "npcs",
+        }
+    }
+
+    /// Record tag of the referenced object
+    pub fn tag(&self) -> &'static str {
+        match self {
+            EObjectType::Other => "",
+            EObjectType::Static => "STAT",
+            EObjectType::Activator => "ACTI",
+            EObjectType::Container => "CONT",
+            EObjectType::Light => "LIGH",
+            EObjectType::Door => "DOOR",
+            EObjectType::Creature => "CREA",
+            EObjectType::Npc => "NPC_",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EObjectType::Other => "Other",
+            EObjectType::Static => "Statics",
+            EObjectType::Activator => "Activators",
+            EObjectType::Container => "Containers",
+            EObjectType::Light => "Lights",
+            EObjectType::Door => "Doors",
+            EObjectType::Creature => "Creatures",
+            EObjectType::Npc => "NPCs",
+        }
+    }
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            EObjectType::Other => Color32::from_rgba_unmultiplied(160, 160, 160, 120),
+            EObjectType::Static => Color32::from_rgba_unmultiplied(200, 200, 200, 80),
+            EObjectType::Activator => Color32::from_rgba_unmultiplied(200, 120, 255, 160),
+            EObjectType::Container => Color32::from_rgba_unmultiplied(80, 160, 255, 200),
+            EObjectType::Light => Color32::from_rgba_unmultiplied(255, 220, 80, 80),
+            EObjectType::Door => Color32::from_rgb(200, 120, 40),
+            EObjectType::Creature => Color32::from_rgb(230, 60, 60),
+            EObjectType::Npc => Color32::from_rgb(60, 220, 60),
+        }
+    }
+
+    /// Marker size in game units. Mesh bounds are not loaded, so this stands in for them
+    pub fn size(&self) -> f32 {
+        match self {
+            EObjectType::Other => 24.0,
+            EObjectType::Static => 128.0,
+            EObjectType::Activator => 64.0,
+            EObjectType::Container => 48.0,
+            EObjectType::Light => 96.0,
+            EObjectType::Door => 96.0,
+            EObjectType::Creature => 48.0,
+            EObjectType::Npc => 48.0,
+        }
+    }
+}
+
+/// Reference position in plan space: game units with y pointing south, like the screen
+pub fn get_plan_pos(reference: &Reference) -> Pos2 {
+    pos2(reference.translation[0], -reference.translation[1])
+}
+
+/// All references of an interior or exterior cell that are not deleted
+pub fn get_placed_references(cell: &Cell) -> impl Iterator<Item = &Reference> {
+    cell.references.values().filter(|r| r.deleted.is_none())
+}
+
+/// Bounding rect of all references in plan space, with some padding
+pub fn get_interior_bounds(cell: &Cell) -> Rect {
+    let mut bounds = Rect::NOTHING;
+    for reference in get_placed_references(cell) {
+        bounds.extend_with(get_plan_pos(reference));
+    }
+    if !bounds.is_finite() {
+        bounds = Rect::from_center_size(Pos2::ZERO, Vec2::ZERO);
+    }
+    bounds.expand(256.0)
+}
+
+pub fn get_object_type(object_types: &HashMap<String, EObjectType>, id: &str) -> EObjectType {
+    object_types
+        .get(&id.to_lowercase())
+        .copied()
+        .unwrap_or_default()
+}
+
+/// One list of marker shapes per object type, in drawing order
+pub fn get_interior_layers(
+    to_screen: RectTransform,
+    cell: &Cell,
+    object_types: &HashMap<String, EObjectType>,
+) -> Vec<(EObjectType, Vec<Shape>)> {
+    let mut layers: Vec<(EObjectType, Vec<Shape>)> =
+        EObjectType::ALL.iter().map(|t| (*t, vec![])).collect();
+    let scale = to_screen.scale().x;
+
+    for reference in get_placed_references(cell) {
+        let object_type = get_object_type(object_types, &reference.id);
+        let center = to_screen * get_plan_pos(reference);
+        let size = (object_type.size() * reference.scale.unwrap_or(1.0) * scale).max(3.0);
+        let color = object_type.color();
+
+        let shape = match object_type {
+            EObjectType::Light | EObjectType::Creature | EObjectType::Npc => {
+                Shape::circle_filled(center, size / 2.0, color)
+            }
+            _ => {
+                // squares turned by the reference's z rotation
+                let angle = -reference.rotation[2];
+                let points = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .iter()
+                    .map(|(x, y)| center + Rot2::from_angle(angle) * vec2(*x, *y) * size / 2.0)
+                    .collect();
+                let stroke = if object_type == EObjectType::Door {
+                    Stroke::new(1.0, Color32::WHITE)
+                } else {
+                    Stroke::NONE
+                };
+                Shape::convex_polygon(points, color, stroke)
+            }
+        };
+
+        if let Some((_, shapes)) = layers.iter_mut().find(|(t, _)| *t == object_type) {
+            shapes.push(shape);
+        }
+    }
+
+    layers
+}
+
+/// The reference closest to a position in plan space, within its marker size
+pub fn get_reference_at<'a>(
+    cell: &'a Cell,
+    object_types: &HashMap<String, EObjectType>,
+    pos: Pos2,
+) -> Option<&'a Reference> {
+    get_placed_references(cell)
+        .filter(|r| {
+            let size = get_object_type(object_types, &r.id).size() * r.scale.unwrap_or(1.0);
+            get_plan_pos(r).distance(pos) <= size / 2.0
+        })
+        .min_by(|a, b| {
+            get_plan_pos(a)
+                .distance(pos)
+                .total_cmp(&get_plan_pos(b).distance(pos))
+        })
+}
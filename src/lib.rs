@@ -13,7 +13,7 @@ use image::{
     error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind},
     DynamicImage, ImageError, RgbaImage,
 };
-use log::{info, warn};
+use log::warn;
 use seahash::hash;
 use serde::{Deserialize, Serialize};
 use tes3::esp::{
@@ -21,9 +21,13 @@ use tes3::esp::{
 };
 
 pub use app::TemplateApp;
-use dimensions::Dimensions;
+pub use dimensions::Dimensions;
+pub use vfs::Vfs;
+pub use world::{EOverlay, WorldData};
 
 use crate::app::TooltipInfo;
+use crate::interior::EObjectType;
+use crate::planner::RouteStep;
 
 use rand::Rng;
 use rand::rngs::StdRng;
@@ -33,17 +37,35 @@ use std::f32::consts::PI;
 
 mod app;
 mod background;
+pub mod cli;
+pub mod conflicts;
+pub mod diff;
 mod dimensions;
+pub mod doors;
 mod eframe_app;
+pub mod export;
+pub mod import;
+pub mod interior;
+pub mod load_order;
+mod loader;
 mod overlay;
+pub mod planner;
+pub mod region;
+pub mod seams;
+mod tiles;
+pub mod travel;
+pub mod vfs;
 mod views;
+pub mod world;
 
 const GRID_SIZE: usize = 16;
 const VERTEX_CNT: usize = 65;
+/// size of an exterior cell in game units
+const CELL_SIZE: f32 = 8192.0;
 const DEFAULT_COLOR: Color32 = Color32::TRANSPARENT;
 
-type CellKey = (i32, i32);
-type ImageBuffer = image::ImageBuffer<image::Rgba<u8>, Vec<u8>>;
+pub type CellKey = (i32, i32);
+pub type ImageBuffer = image::ImageBuffer<image::Rgba<u8>, Vec<u8>>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum EBackground {
@@ -53,6 +75,71 @@ pub enum EBackground {
     #[default]
     GameMap,
     PTMap,
+    Hillshade,
+    Slope,
+    Aspect,
+}
+
+impl EBackground {
+    /// Landscape, heightmap and the terrain analysis backgrounds are drawn as tiles
+    /// with a level of detail in the map view
+    pub fn is_tiled(&self) -> bool {
+        matches!(
+            self,
+            EBackground::Landscape
+                | EBackground::HeightMap
+                | EBackground::Hillshade
+                | EBackground::Slope
+                | EBackground::Aspect
+        )
+    }
+
+    /// Backgrounds computed from the slope of the vertex heights
+    pub fn is_terrain(&self) -> bool {
+        matches!(
+            self,
+            EBackground::Hillshade | EBackground::Slope | EBackground::Aspect
+        )
+    }
+}
+
+/// Where the game map background takes the 9 x 9 heights of a cell from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum EWorldMapSource {
+    /// the world map heights (WNAM) stored in the LAND record, what the game shows
+    #[default]
+    Wnam,
+    /// calculated from the vertex heights
+    VertexHeights,
+}
+
+impl EWorldMapSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EWorldMapSource::Wnam => "World map data (WNAM)",
+            EWorldMapSource::VertexHeights => "Vertex heights",
+        }
+    }
+}
+
+/// Game map background and the stale world map overlay
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GameMapSettings {
+    pub source: EWorldMapSource,
+    /// largest difference of the stored world map heights to the vertex heights, in world map
+    /// steps (128 units above sea level, 16 below), that is not drawn as stale.
+    /// 1 allows for rounding in the construction set
+    pub stale_tolerance: u8,
+}
+
+impl Default for GameMapSettings {
+    fn default() -> Self {
+        Self {
+            source: EWorldMapSource::Wnam,
+            stale_tolerance: 1,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -79,6 +166,160 @@ impl Default for LandscapeSettings {
     }
 }
 
+/// Which references the references overlay draws
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReferenceFilter {
+    pub types: Vec<EObjectType>,
+    /// case insensitive substring of the object id
+    pub id: String,
+}
+
+impl ReferenceFilter {
+    pub fn matches(&self, object_type: EObjectType, id: &str) -> bool {
+        self.types.contains(&object_type)
+            && (self.id.is_empty() || id.to_lowercase().contains(&self.id.to_lowercase()))
+    }
+}
+
+impl Default for ReferenceFilter {
+    fn default() -> Self {
+        Self {
+            // statics are most of the references
+            types: vec![
+                EObjectType::Npc,
+                EObjectType::Creature,
+                EObjectType::Container,
+                EObjectType::Door,
+                EObjectType::Activator,
+                EObjectType::Light,
+            ],
+            id: String::new(),
+        }
+    }
+}
+
+/// Look of the regions overlay
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RegionStyle {
+    /// opacity of the region colors, 0 to only draw borders and labels
+    pub fill_opacity: f32,
+    /// lines where the region changes between neighboring cells
+    pub borders: bool,
+    pub border_width: f32,
+    pub border_color: Color32,
+    /// region names at the centroid of their cells
+    pub labels: bool,
+    pub label_size: f32,
+    pub label_color: Color32,
+}
+
+impl Default for RegionStyle {
+    fn default() -> Self {
+        Self {
+            fill_opacity: 0.5,
+            borders: true,
+            border_width: 1.5,
+            border_color: Color32::BLACK,
+            labels: true,
+            label_size: 14.0,
+            label_color: Color32::WHITE,
+        }
+    }
+}
+
+/// Look of the place names overlay
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LabelSettings {
+    /// font size relative to the size of a cell on screen
+    pub scale: f32,
+    /// font size limits in pixels
+    pub min_size: f32,
+    pub max_size: f32,
+    pub color: Color32,
+}
+
+impl Default for LabelSettings {
+    fn default() -> Self {
+        Self {
+            scale: 0.25,
+            min_size: 9.0,
+            max_size: 32.0,
+            color: Color32::WHITE,
+        }
+    }
+}
+
+/// Look of the contour lines and coastline overlays
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ContourSettings {
+    /// height difference between two contour lines in game units
+    pub interval: f32,
+    /// every n-th line is an index contour
+    pub index_every: u32,
+    pub width: f32,
+    pub index_width: f32,
+    pub color: Color32,
+    /// heights on the index contours when zoomed in
+    pub labels: bool,
+    pub label_size: f32,
+    pub coastline_width: f32,
+    pub coastline_color: Color32,
+}
+
+impl Default for ContourSettings {
+    fn default() -> Self {
+        Self {
+            interval: 256.0,
+            index_every: 5,
+            width: 1.0,
+            index_width: 2.0,
+            color: Color32::from_rgb(120, 80, 40),
+            labels: true,
+            label_size: 11.0,
+            coastline_width: 2.0,
+            coastline_color: Color32::from_rgb(20, 60, 160),
+        }
+    }
+}
+
+/// Colors of the travel overlay
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TravelSettings {
+    /// route color by NPC class, matched case insensitive
+    pub class_colors: Vec<(String, Color32)>,
+    /// color of classes not in the table
+    pub default_color: Color32,
+}
+
+impl TravelSettings {
+    pub fn get_color(&self, class: &str) -> Color32 {
+        self.class_colors
+            .iter()
+            .find(|(c, _)| c.eq_ignore_ascii_case(class))
+            .map(|(_, color)| *color)
+            .unwrap_or(self.default_color)
+    }
+}
+
+impl Default for TravelSettings {
+    fn default() -> Self {
+        Self {
+            class_colors: vec![
+                ("Shipmaster".to_owned(), Color32::BLUE),
+                ("Caravaner".to_owned(), Color32::GOLD),
+                ("Gondolier".to_owned(), Color32::GRAY),
+                ("T_Mw_RiverstriderService".to_owned(), Color32::LIGHT_BLUE),
+            ],
+            default_color: Color32::RED,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct HeightmapSettings {
     pub depth_spectrum: i32,  // heightmap
@@ -100,6 +341,34 @@ impl Default for HeightmapSettings {
     }
 }
 
+/// Sun and colors of the hillshade, slope and aspect backgrounds
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TerrainSettings {
+    /// direction the sun shines from in degrees, clockwise from north
+    pub azimuth: f32,
+    /// sun angle above the horizon in degrees
+    pub altitude: f32,
+    /// vertical exaggeration of the hillshade
+    pub z_factor: f32,
+    /// slope in degrees drawn in the darkest color
+    pub max_slope: f32,
+    /// opacity of the hillshade under the game map and landscape backgrounds, 0 is off
+    pub hillshade_blend: f32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            azimuth: 315.0,
+            altitude: 45.0,
+            z_factor: 1.0,
+            max_slope: 45.0,
+            hillshade_blend: 0.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
 pub struct SavedData {
     // background
@@ -117,6 +386,24 @@ pub struct SavedData {
     pub overlay_kyn_interventions: bool,
     pub overlay_travel: bool,
     pub overlay_conflicts: bool,
+    #[serde(default)]
+    pub overlay_diff: bool,
+    #[serde(default)]
+    pub overlay_seams: bool,
+    #[serde(default)]
+    pub overlay_references: bool,
+    #[serde(default)]
+    pub overlay_doors: bool,
+    #[serde(default)]
+    pub overlay_weather: bool,
+    #[serde(default)]
+    pub overlay_labels: bool,
+    #[serde(default)]
+    pub overlay_contours: bool,
+    #[serde(default)]
+    pub overlay_coastline: bool,
+    #[serde(default)]
+    pub overlay_world_map: bool,
 
     pub show_tooltips: bool,
 
@@ -125,12 +412,96 @@ pub struct SavedData {
     // settings
     pub landscape_settings: LandscapeSettings,
     pub heightmap_settings: HeightmapSettings,
+    #[serde(default)]
+    pub terrain_settings: TerrainSettings,
+    #[serde(default)]
+    pub gamemap_settings: GameMapSettings,
+    #[serde(default)]
+    pub reference_filter: ReferenceFilter,
+    #[serde(default)]
+    pub travel_settings: TravelSettings,
+    #[serde(default)]
+    pub region_style: RegionStyle,
+    #[serde(default)]
+    pub label_settings: LabelSettings,
+    #[serde(default)]
+    pub contour_settings: ContourSettings,
+}
+
+impl SavedData {
+    /// All enabled overlays in drawing order
+    pub fn get_overlays(&self) -> Vec<EOverlay> {
+        EOverlay::ALL
+            .into_iter()
+            .filter(|o| self.is_overlay_enabled(*o))
+            .collect()
+    }
+
+    pub fn is_overlay_enabled(&self, overlay: EOverlay) -> bool {
+        match overlay {
+            EOverlay::Paths => self.overlay_paths,
+            EOverlay::Regions => self.overlay_region,
+            EOverlay::Weather => self.overlay_weather,
+            EOverlay::Grid => self.overlay_grid,
+            EOverlay::AlmsiviInterventions => self.overlay_alm_interventions,
+            EOverlay::DivineInterventions => self.overlay_div_interventions,
+            EOverlay::KyneInterventions => self.overlay_kyn_interventions,
+            EOverlay::Cities => self.overlay_cities,
+            EOverlay::Travel => self.overlay_travel,
+            EOverlay::Conflicts => self.overlay_conflicts,
+            EOverlay::Diff => self.overlay_diff,
+            EOverlay::Seams => self.overlay_seams,
+            EOverlay::References => self.overlay_references,
+            EOverlay::Doors => self.overlay_doors,
+            EOverlay::Labels => self.overlay_labels,
+            EOverlay::Contours => self.overlay_contours,
+            EOverlay::Coastline => self.overlay_coastline,
+            EOverlay::WorldMap => self.overlay_world_map,
+        }
+    }
+
+    pub fn set_overlay_enabled(&mut self, overlay: EOverlay, enabled: bool) {
+        match overlay {
+            EOverlay::Paths => self.overlay_paths = enabled,
+            EOverlay::Regions => self.overlay_region = enabled,
+            EOverlay::Weather => self.overlay_weather = enabled,
+            EOverlay::Grid => self.overlay_grid = enabled,
+            EOverlay::AlmsiviInterventions => self.overlay_alm_interventions = enabled,
+            EOverlay::DivineInterventions => self.overlay_div_interventions = enabled,
+            EOverlay::KyneInterventions => self.overlay_kyn_interventions = enabled,
+            EOverlay::Cities => self.overlay_cities = enabled,
+            EOverlay::Travel => self.overlay_travel = enabled,
+            EOverlay::Conflicts => self.overlay_conflicts = enabled,
+            EOverlay::Diff => self.overlay_diff = enabled,
+            EOverlay::Seams => self.overlay_seams = enabled,
+            EOverlay::References => self.overlay_references = enabled,
+            EOverlay::Doors => self.overlay_doors = enabled,
+            EOverlay::Labels => self.overlay_labels = enabled,
+            EOverlay::Contours => self.overlay_contours = enabled,
+            EOverlay::Coastline => self.overlay_coastline = enabled,
+            EOverlay::WorldMap => self.overlay_world_map = enabled,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct RuntimeData {
     pub plugin_filter: String,
     pub cell_filter: String,
+    pub interior_filter: String,
+    pub interiors_by_entrance: bool,
+    /// conflicts panel lists only cells with replaced landscape edits
+    pub conflicts_landscape_only: bool,
+
+    // route planner, picked in the map context menu
+    pub route_start: Option<CellKey>,
+    pub route_end: Option<CellKey>,
+    pub route_mark: Option<CellKey>,
+    pub route: Vec<RouteStep>,
+
+    /// region shown in the regions panel and outlined on the map
+    pub selected_region: Option<String>,
+    pub region_filter: String,
 
     pub info: TooltipInfo,
 
@@ -161,7 +532,7 @@ impl Default for ZoomData {
     }
 }
 
-#[derive(Default, Clone)]
+#[derive(Debug, Default, Clone)]
 pub struct PluginViewModel {
     pub hash: u64,
     pub path: PathBuf,
@@ -244,7 +615,7 @@ fn overlay_colors_with_alpha(color1: Color32, color2: Color32, alpha1: f32) -> C
     Color32::from_rgba_premultiplied(r, g, b, 255)
 }
 
-fn color_image_to_dynamic_image(color_image: &ColorImage) -> Result<DynamicImage, ImageError> {
+fn color_image_to_rgba_image(color_image: &ColorImage) -> Result<RgbaImage, ImageError> {
     let pixels = color_image.as_raw();
 
     // Create an RgbaImage from the raw pixel data
@@ -253,8 +624,7 @@ fn color_image_to_dynamic_image(color_image: &ColorImage) -> Result<DynamicImage
         color_image.height() as u32,
         pixels.to_vec(),
     ) {
-        // Convert the RgbaImage to a DynamicImage (required for saving as PNG)
-        Ok(DynamicImage::ImageRgba8(img))
+        Ok(img)
     } else {
         let e = ImageError::Unsupported(UnsupportedError::from_format_and_kind(
             ImageFormatHint::Name("".to_owned()),
@@ -285,72 +655,40 @@ fn calculate_dimensions(
     })
 }
 
-fn load_texture(
-    data_files: &Option<PathBuf>,
-    ltex: &LandscapeTexture,
-) -> Result<DynamicImage, ImageError> {
-    let data_files = match data_files {
-        Some(p) => p,
-        None => {
-            warn!("Data files not found");
-            return Err(ImageError::IoError(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Data files not found",
-            )));
-        }
-    };
-
-    let _tex_path = data_files.join("Textures").join(ltex.file_name.clone());
+fn load_texture(vfs: &Vfs, ltex: &LandscapeTexture) -> Result<DynamicImage, ImageError> {
+    let tex_path = Path::new("Textures").join(ltex.file_name.clone());
 
-    let tga_path = _tex_path.with_extension("tga");
-    let dds_path = _tex_path.with_extension("dds");
-    let bmp_path = _tex_path.with_extension("bmp");
-
-    if !tga_path.exists() && !dds_path.exists() && !bmp_path.exists() {
-        return Err(ImageError::IoError(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Texture not found",
-        )));
+    // prefer dds over tga and bmp
+    for ext in ["dds", "tga", "bmp"] {
+        let path = tex_path.with_extension(ext).to_string_lossy().to_string();
+        if vfs.exists(&path) {
+            let bytes = vfs.read(&path).map_err(ImageError::IoError)?;
+            return decode_image(&bytes, ext);
+        }
     }
 
-    // decode image
-    if dds_path.exists() {
-        decode_image(dds_path)
-    } else if tga_path.exists() {
-        decode_image(tga_path)
-    } else if bmp_path.exists() {
-        decode_image(bmp_path)
-    } else {
-        Err(ImageError::IoError(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Texture not found",
-        )))
-    }
-}
-
-fn decode_image(tex_path: PathBuf) -> Result<DynamicImage, ImageError> {
-    let mut reader = image::ImageReader::open(&tex_path)?;
-    let ext = tex_path
-        .extension()
-        .unwrap()
-        .to_string_lossy()
-        .to_lowercase();
-    if ext.contains("tga") {
-        reader.set_format(image::ImageFormat::Tga);
-    } else if ext.contains("dds") {
-        reader.set_format(image::ImageFormat::Dds);
-    } else if ext.contains("bmp") {
-        reader.set_format(image::ImageFormat::Bmp);
-    } else {
-        return Err(ImageError::Unsupported(
-            UnsupportedError::from_format_and_kind(
-                ImageFormatHint::Name(ext.clone()),
-                UnsupportedErrorKind::Format(ImageFormatHint::Name(ext)),
-            ),
-        ));
-    }
+    Err(ImageError::IoError(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        "Texture not found",
+    )))
+}
 
-    reader.decode()
+fn decode_image(bytes: &[u8], ext: &str) -> Result<DynamicImage, ImageError> {
+    let format = match ext {
+        "tga" => image::ImageFormat::Tga,
+        "dds" => image::ImageFormat::Dds,
+        "bmp" => image::ImageFormat::Bmp,
+        _ => {
+            return Err(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormatHint::Name(ext.to_owned()),
+                    UnsupportedErrorKind::Format(ImageFormatHint::Name(ext.to_owned())),
+                ),
+            ));
+        }
+    };
+
+    image::load_from_memory_with_format(bytes, format)
 }
 
 pub fn get_cell_name(cells: &HashMap<CellKey, Cell>, pos: CellKey) -> String {
@@ -366,6 +704,62 @@ pub fn get_cell_name(cells: &HashMap<CellKey, Cell>, pos: CellKey) -> String {
     format!("{} ({},{})", name, pos.0, pos.1)
 }
 
+/// Decoded vertex heights of a landscape record in game units, rows from south to north
+pub fn get_landscape_heights(landscape: &Landscape) -> Option<[[f32; 65]; 65]> {
+    if !landscape
+        .landscape_flags
+        .contains(LandscapeFlags::USES_VERTEX_HEIGHTS_AND_NORMALS)
+    {
+        return None;
+    }
+
+    // get data
+    let data = &landscape.vertex_heights.data;
+    let mut heights: [[f32; 65]; 65] = [[0.0; VERTEX_CNT]; VERTEX_CNT];
+    for y in 0..VERTEX_CNT {
+        for x in 0..VERTEX_CNT {
+            heights[y][x] = data[y][x] as f32;
+        }
+    }
+
+    // decode
+    let mut offset: f32 = landscape.vertex_heights.offset;
+    for row in heights.iter_mut().take(VERTEX_CNT) {
+        for x in row.iter_mut().take(VERTEX_CNT) {
+            offset += *x;
+            *x = offset;
+        }
+        offset = row[0];
+    }
+
+    for row in &mut heights {
+        for height in row {
+            *height *= 8.0;
+        }
+    }
+
+    Some(heights)
+}
+
+/// The 9 x 9 low resolution heights of the world map (WNAM) for vertex heights in game units,
+/// rows from south to north. Like the construction set, every 8th vertex is divided by 128
+/// above sea level and by 16 at or below it
+pub fn get_world_map_data(heights: &[[f32; 65]; 65]) -> [[i8; 9]; 9] {
+    let mut data = [[0; 9]; 9];
+    for (y, row) in data.iter_mut().enumerate() {
+        for (x, value) in row.iter_mut().enumerate() {
+            let height = heights[y * 8][x * 8];
+            let scaled = if height > 0.0 {
+                height / 128.0
+            } else {
+                height / 16.0
+            };
+            *value = scaled.clamp(-128.0, 127.0) as i8;
+        }
+    }
+    data
+}
+
 pub fn calculate_heights(
     landscape_records: &HashMap<CellKey, Landscape>,
     dimensions: &mut Dimensions,
@@ -377,35 +771,10 @@ pub fn calculate_heights(
     for cy in dimensions.min_y..dimensions.max_y + 1 {
         for cx in dimensions.min_x..dimensions.max_x + 1 {
             if let Some(landscape) = landscape_records.get(&(cx, cy)) {
-                if landscape
-                    .landscape_flags
-                    .contains(LandscapeFlags::USES_VERTEX_HEIGHTS_AND_NORMALS)
-                {
-                    // get vertex data
-                    // get data
-                    let data = &landscape.vertex_heights.data;
-                    let mut heights: [[f32; 65]; 65] = [[0.0; VERTEX_CNT]; VERTEX_CNT];
-                    for y in 0..VERTEX_CNT {
-                        for x in 0..VERTEX_CNT {
-                            heights[y][x] = data[y][x] as f32;
-                        }
-                    }
-
-                    // decode
-                    let mut offset: f32 = landscape.vertex_heights.offset;
-                    for row in heights.iter_mut().take(VERTEX_CNT) {
-                        for x in row.iter_mut().take(VERTEX_CNT) {
-                            offset += *x;
-                            *x = offset;
-                        }
-                        offset = row[0];
-                    }
-
-                    for row in &mut heights {
-                        for height in row {
-                            *height *= 8.0;
-
-                            let z = *height;
+                if let Some(heights) = get_landscape_heights(landscape) {
+                    for row in &heights {
+                        for z in row {
+                            let z = *z;
                             if let Some(minz) = min_z {
                                 if z < minz {
                                     min_z = Some(z);
@@ -507,6 +876,34 @@ fn get_rect_at_cell(dimensions: &Dimensions, to_screen: RectTransform, key: Cell
     Rect::from_two_pos(to_screen * p00, to_screen * p11)
 }
 
+/// Screen position of a translation in game units
+fn get_pos_at_translation(
+    dimensions: &Dimensions,
+    to_screen: RectTransform,
+    translation: [f32; 3],
+) -> Pos2 {
+    let x = translation[0] / CELL_SIZE - dimensions.min_x as f32;
+    let y = (dimensions.max_y + 1) as f32 - translation[1] / CELL_SIZE;
+    to_screen * Pos2::new(x, y)
+}
+
+/// The exterior cell a position in game units lies in
+fn get_cell_at_translation(translation: [f32; 3]) -> CellKey {
+    (
+        (translation[0] / CELL_SIZE).floor() as i32,
+        (translation[1] / CELL_SIZE).floor() as i32,
+    )
+}
+
+/// Distance between two positions in game units
+fn get_distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
 fn get_long_tri_at_cell(dimensions: &Dimensions, to_screen: RectTransform, key: CellKey) -> Vec<Pos2> {
     let p00 = dimensions.tranform_to_canvas(key);
 
@@ -654,3 +1051,30 @@ fn rect_to_edges(rect: Rect) -> Vec<Shape> {
 pub fn get_unique_id(record: &TES3Object) -> String {
     format!("{},{}", record.tag_str(), record.editor_id())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_map_data_above_and_below_sea_level() {
+        let mut heights = [[0.0; VERTEX_CNT]; VERTEX_CNT];
+        heights[0][0] = 1000.0;
+        heights[0][8] = -1000.0;
+        heights[8][0] = 127.0;
+        heights[8][8] = -15.0;
+        heights[16][0] = 100_000.0;
+        heights[16][8] = -100_000.0;
+        // not sampled
+        heights[1][1] = 5000.0;
+
+        let data = get_world_map_data(&heights);
+        assert_eq!(data[0][0], 7);
+        assert_eq!(data[0][1], -62);
+        assert_eq!(data[1][0], 0);
+        assert_eq!(data[1][1], 0);
+        assert_eq!(data[2][0], 127);
+        assert_eq!(data[2][1], -128);
+        assert_eq!(data[8][8], 0);
+    }
+}
@@ -0,0 +1,405 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{get_plugins_sorted, PluginViewModel};
+
+/// Where the plugin load order comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ELoadOrderSource {
+    /// all plugins in the data files folder, sorted by modification time
+    #[default]
+    FileTime,
+    /// [Game Files] in Morrowind.ini
+    MorrowindIni,
+    /// data= and content= in openmw.cfg
+    OpenMWCfg,
+}
+
+impl ELoadOrderSource {
+    /// Default location of the config file for this source
+    pub fn default_config_path(&self, data_files: Option<&Path>) -> Option<PathBuf> {
+        match self {
+            ELoadOrderSource::FileTime => None,
+            ELoadOrderSource::MorrowindIni => Some(data_files?.parent()?.join("Morrowind.ini")),
+            ELoadOrderSource::OpenMWCfg => {
+                let path = if cfg!(windows) {
+                    PathBuf::from(std::env::var_os("USERPROFILE")?)
+                        .join("Documents")
+                        .join("My Games")
+                        .join("OpenMW")
+                } else if cfg!(target_os = "macos") {
+                    PathBuf::from(std::env::var_os("HOME")?)
+                        .join("Library")
+                        .join("Preferences")
+                        .join("openmw")
+                } else if let Some(config_home) = std::env::var_os("XDG_CONFIG_HOME") {
+                    PathBuf::from(config_home).join("openmw")
+                } else {
+                    PathBuf::from(std::env::var_os("HOME")?)
+                        .join(".config")
+                        .join("openmw")
+                };
+                Some(path.join("openmw.cfg"))
+            }
+        }
+    }
+}
+
+/// Data directories, archives and content files read from a game config
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadOrder {
+    /// data directories, lowest priority first
+    pub data_dirs: Vec<PathBuf>,
+    /// bsa file names in load order
+    pub archives: Vec<String>,
+    /// plugin file names in load order
+    pub content: Vec<String>,
+}
+
+impl LoadOrder {
+    /// Reads a load order from the given source.
+    /// If no config is given, the default location is used.
+    /// Only the file time source needs a data files folder
+    pub fn from_source(
+        source: ELoadOrderSource,
+        config: Option<&Path>,
+        data_files: Option<&Path>,
+    ) -> std::io::Result<Self> {
+        let config = match config {
+            Some(config) => Some(config.to_path_buf()),
+            None => source.default_config_path(data_files),
+        };
+
+        match (source, config) {
+            (ELoadOrderSource::FileTime, _) => match data_files {
+                Some(data_files) => Ok(LoadOrder {
+                    data_dirs: vec![data_files.to_path_buf()],
+                    archives: vec![MORROWIND_BSA.to_owned()],
+                    content: vec![],
+                }),
+                None => Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "No data files folder",
+                )),
+            },
+            (ELoadOrderSource::MorrowindIni, Some(config)) => parse_morrowind_ini(&config),
+            (ELoadOrderSource::OpenMWCfg, Some(config)) => parse_openmw_cfg(&config),
+            (_, None) => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Config file not found",
+            )),
+        }
+    }
+
+    /// Get all plugins in the data directories.
+    /// Plugins in the content list come first and in that order and are enabled
+    pub fn get_plugins(&self, use_omw_plugins: bool) -> Vec<PluginViewModel> {
+        // later data directories override earlier ones
+        let mut files: HashMap<String, PathBuf> = HashMap::default();
+        let mut all_plugins: Vec<PathBuf> = vec![];
+        for dir in &self.data_dirs {
+            for path in get_plugins_sorted(dir, use_omw_plugins) {
+                let name = path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_lowercase();
+                if let Some(previous) = files.insert(name, path.clone()) {
+                    all_plugins.retain(|p| *p != previous);
+                }
+                all_plugins.push(path);
+            }
+        }
+
+        let mut vms: Vec<PluginViewModel> = vec![];
+        for name in &self.content {
+            if let Some(path) = files.get(&name.to_lowercase()) {
+                if vms.iter().any(|vm| vm.path == *path) {
+                    continue;
+                }
+                let mut vm = PluginViewModel::from_path(path.clone());
+                vm.enabled = true;
+                vms.push(vm);
+            } else {
+                warn!("Plugin {} not found in data directories", name);
+            }
+        }
+
+        // remaining plugins are disabled
+        for path in all_plugins {
+            if !vms.iter().any(|vm| vm.path == path) {
+                vms.push(PluginViewModel::from_path(path));
+            }
+        }
+
+        vms
+    }
+}
+
+fn read_config(path: &Path) -> std::io::Result<String> {
+    // Morrowind.ini is usually not utf8
+    let bytes = fs::read(path)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Morrowind.bsa is always loaded first by the engine and not listed in the ini
+const MORROWIND_BSA: &str = "Morrowind.bsa";
+
+/// Parse [Game Files] and [Archives] from a Morrowind.ini.
+/// The data directory is "Data Files" next to the ini
+pub fn parse_morrowind_ini(path: &Path) -> std::io::Result<LoadOrder> {
+    let text = read_config(path)?;
+
+    let mut game_files: Vec<(u32, String)> = vec![];
+    let mut archives: Vec<(u32, String)> = vec![];
+    let mut section = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            section = line.to_lowercase();
+            continue;
+        }
+        if line.starts_with(';') {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+
+            let (prefix, list) = match section.as_str() {
+                "[game files]" => ("gamefile", &mut game_files),
+                "[archives]" => ("archive", &mut archives),
+                _ => continue,
+            };
+            if let Some(index) = key.strip_prefix(prefix) {
+                if let Ok(index) = index.trim().parse::<u32>() {
+                    list.push((index, value.to_owned()));
+                }
+            }
+        }
+    }
+    game_files.sort_by_key(|(index, _)| *index);
+    archives.sort_by_key(|(index, _)| *index);
+
+    let data_dir = path
+        .parent()
+        .map(|p| p.join("Data Files"))
+        .unwrap_or_default();
+
+    let mut archive_names = vec![MORROWIND_BSA.to_owned()];
+    for (_, name) in archives {
+        if !name.eq_ignore_ascii_case(MORROWIND_BSA) {
+            archive_names.push(name);
+        }
+    }
+
+    Ok(LoadOrder {
+        data_dirs: vec![data_dir],
+        archives: archive_names,
+        content: game_files.into_iter().map(|(_, name)| name).collect(),
+    })
+}
+
+/// Parse data=, fallback-archive= and content= lines from an openmw.cfg
+pub fn parse_openmw_cfg(path: &Path) -> std::io::Result<LoadOrder> {
+    let text = read_config(path)?;
+    let cfg_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+    let mut load_order = LoadOrder::default();
+    // data-local has the highest priority, whatever its position in the file
+    let mut data_local = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match key.trim() {
+            "data" => {
+                if let Some(data_dir) = parse_openmw_path(value, &cfg_dir) {
+                    if !load_order.data_dirs.contains(&data_dir) {
+                        load_order.data_dirs.push(data_dir);
+                    }
+                }
+            }
+            "data-local" => data_local = parse_openmw_path(value, &cfg_dir),
+            "fallback-archive" => load_order.archives.push(value.to_owned()),
+            "content" => load_order.content.push(value.to_owned()),
+            "replace" => match value {
+                "data" => load_order.data_dirs.clear(),
+                "data-local" => data_local = None,
+                "fallback-archive" => load_order.archives.clear(),
+                "content" => load_order.content.clear(),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    if let Some(data_local) = data_local {
+        load_order.data_dirs.retain(|dir| *dir != data_local);
+        load_order.data_dirs.push(data_local);
+    }
+
+    Ok(load_order)
+}
+
+/// openmw.cfg paths may be quoted, with & as escape character.
+/// Returns None for paths in the global data directory, which depends on how OpenMW was built
+fn parse_openmw_path(value: &str, cfg_dir: &Path) -> Option<PathBuf> {
+    let mut path = String::new();
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '&' => {
+                    if let Some(escaped) = chars.next() {
+                        path.push(escaped);
+                    }
+                }
+                '"' => break,
+                _ => path.push(c),
+            }
+        }
+    } else {
+        path.push_str(value);
+    }
+
+    // relative paths are relative to the config
+    if let Some(rest) = path.strip_prefix("?local?") {
+        return Some(cfg_dir.join(rest));
+    }
+    if let Some(rest) = path.strip_prefix("?userdata?") {
+        return match get_openmw_user_data_dir() {
+            Some(dir) => Some(dir.join(rest)),
+            None => {
+                warn!("Skipping data directory {}: no user data directory", path);
+                None
+            }
+        };
+    }
+    if path.starts_with("?global?") {
+        warn!(
+            "Skipping data directory {}: unknown global data directory",
+            path
+        );
+        return None;
+    }
+    let path = PathBuf::from(path);
+    if path.is_relative() {
+        Some(cfg_dir.join(path))
+    } else {
+        Some(path)
+    }
+}
+
+/// The ?userdata? directory of OpenMW, where it keeps saves and screenshots
+fn get_openmw_user_data_dir() -> Option<PathBuf> {
+    let home =
+        std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from);
+    if cfg!(windows) {
+        Some(home?.join("Documents").join("My Games").join("OpenMW"))
+    } else if cfg!(target_os = "macos") {
+        Some(
+            home?
+                .join("Library")
+                .join("Application Support")
+                .join("openmw"),
+        )
+    } else {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| Some(home?.join(".local").join("share")))?;
+        Some(data_home.join("openmw"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, text: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tes3map-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn morrowind_ini_game_files_and_archives() {
+        let path = write_config(
+            "Morrowind.ini",
+            "[General]\r\n\
+             GameFile0=NotAGameFile.esp\r\n\
+             [Game Files]\r\n\
+             GameFile1=Tribunal.esm\r\n\
+             ;GameFile2=Disabled.esp\r\n\
+             GameFile0=Morrowind.esm\r\n\
+             GameFile3=\r\n\
+             [Archives]\r\n\
+             Archive 1=Bloodmoon.bsa\r\n\
+             Archive 0=Tribunal.bsa\r\n\
+             Archive 2=morrowind.bsa\r\n",
+        );
+
+        let load_order = parse_morrowind_ini(&path).unwrap();
+        assert_eq!(load_order.content, ["Morrowind.esm", "Tribunal.esm"]);
+        assert_eq!(
+            load_order.archives,
+            ["Morrowind.bsa", "Tribunal.bsa", "Bloodmoon.bsa"]
+        );
+        assert_eq!(
+            load_order.data_dirs,
+            [path.parent().unwrap().join("Data Files")]
+        );
+    }
+
+    #[test]
+    fn openmw_cfg_data_archives_and_content() {
+        let path = write_config(
+            "openmw.cfg",
+            "# comment\n\
+             data=\"Morrowind/Data Files\"\n\
+             data-local=\"?local?data\"\n\
+             data=mods/textures\n\
+             data=\"mods/a&&b &\"c&\"\"\n\
+             data=\"?global?data\"\n\
+             data=?userdata?mods\n\
+             data=Morrowind/Data Files\n\
+             data=data\n\
+             fallback-archive=Morrowind.bsa\n\
+             content=Old.esp\n\
+             replace=content\n\
+             content=Morrowind.esm\n\
+             content=Mod.esp\n",
+        );
+        let cfg_dir = path.parent().unwrap();
+
+        let load_order = parse_openmw_cfg(&path).unwrap();
+        let mut data_dirs = vec![
+            cfg_dir.join("Morrowind/Data Files"),
+            cfg_dir.join("mods/textures"),
+            cfg_dir.join("mods/a&b \"c\""),
+        ];
+        data_dirs.extend(get_openmw_user_data_dir().map(|dir| dir.join("mods")));
+        data_dirs.push(cfg_dir.join("data"));
+        assert_eq!(load_order.data_dirs, data_dirs);
+        assert_eq!(load_order.archives, ["Morrowind.bsa"]);
+        assert_eq!(load_order.content, ["Morrowind.esm", "Mod.esp"]);
+    }
+}
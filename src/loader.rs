@@ -0,0 +1,164 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use egui::ColorImage;
+use log::info;
+
+use overlay::paths::get_overlay_path_image;
+
+use crate::*;
+
+/// What a load job needs, copied from the app when it is started
+pub struct LoadRequest {
+    pub plugins: Vec<PluginViewModel>,
+    pub settings: SavedData,
+    /// load the "before" world of a comparison, nothing is rendered
+    pub diff_base: bool,
+}
+
+/// Everything computed on the worker thread
+pub struct LoadResult {
+    pub world: WorldData,
+    /// None for tiled backgrounds, these are rendered by the map view
+    pub background: Option<ColorImage>,
+    pub paths: Option<ColorImage>,
+    pub diff_base: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoadProgress {
+    pub step: usize,
+    pub steps: usize,
+    pub message: String,
+}
+
+impl LoadProgress {
+    pub fn fraction(&self) -> f32 {
+        if self.steps == 0 {
+            return 0.0;
+        }
+        self.step as f32 / self.steps as f32
+    }
+}
+
+/// A running load job: plugins and background are loaded on a worker thread
+pub struct Loader {
+    progress: Arc<Mutex<LoadProgress>>,
+    cancelled: Arc<AtomicBool>,
+    receiver: Receiver<LoadResult>,
+}
+
+pub enum ELoadState {
+    Running,
+    Done(Box<LoadResult>),
+    Cancelled,
+}
+
+impl Loader {
+    pub fn spawn(ctx: &egui::Context, request: LoadRequest) -> Self {
+        let progress = Arc::new(Mutex::new(LoadProgress::default()));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        let thread_progress = progress.clone();
+        let thread_cancelled = cancelled.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let report = |step: usize, steps: usize, message: String| {
+                if let Ok(mut progress) = thread_progress.lock() {
+                    *progress = LoadProgress {
+                        step,
+                        steps,
+                        message,
+                    };
+                }
+                ctx.request_repaint();
+                !thread_cancelled.load(Ordering::Relaxed)
+            };
+
+            // dropping the sender without a result means the job was cancelled
+            if let Some(result) = load(&request, report) {
+                let _ = sender.send(result);
+            }
+            ctx.request_repaint();
+        });
+
+        Self {
+            progress,
+            cancelled,
+            receiver,
+        }
+    }
+
+    pub fn progress(&self) -> LoadProgress {
+        self.progress.lock().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn poll(&self) -> ELoadState {
+        match self.receiver.try_recv() {
+            Ok(result) => ELoadState::Done(Box::new(result)),
+            Err(TryRecvError::Empty) => ELoadState::Running,
+            Err(TryRecvError::Disconnected) => ELoadState::Cancelled,
+        }
+    }
+}
+
+/// Loads the plugins and renders the background.
+/// report is called with (step, steps, message) and returns false if the job was cancelled
+fn load<F>(request: &LoadRequest, report: F) -> Option<LoadResult>
+where
+    F: Fn(usize, usize, String) -> bool,
+{
+    // one step per plugin, then the background
+    let steps = request.plugins.iter().filter(|p| p.enabled).count() + 1;
+
+    let world = WorldData::from_plugins_with_progress(&request.plugins, |i, _, vm| {
+        report(i, steps, format!("Loading {}", vm.get_name()))
+    })?;
+
+    if request.diff_base {
+        return Some(LoadResult {
+            world,
+            background: None,
+            paths: None,
+            diff_base: true,
+        });
+    }
+
+    if !report(steps - 1, steps, "Rendering".to_owned()) {
+        return None;
+    }
+
+    let settings = &request.settings;
+    let background = if world.heights.is_empty() || settings.background.is_tiled() {
+        None
+    } else {
+        world.get_background_image(settings.background, settings, &HashMap::default())
+    };
+    let paths = Some(get_overlay_path_image(
+        &world.dimensions,
+        &world.land_records,
+    ));
+
+    info!("Loaded {} plugins", world.plugins.len());
+    Some(LoadResult {
+        world,
+        background,
+        paths,
+        diff_base: false,
+    })
+}
@@ -7,13 +7,32 @@ const NAME: &str = env!("CARGO_PKG_NAME");
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
-    use log::{warn, LevelFilter};
+    use log::{error, warn, LevelFilter};
 
     let log_path = format!("{}.log", NAME);
     let log_level = LevelFilter::Debug;
     let _ = simple_logging::log_to_file(log_path, log_level);
     warn!("{} v{}", NAME, VERSION);
 
+    // headless mode: tes3map render ...
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "render") {
+        #[cfg(windows)]
+        attach_console();
+
+        let result = tes3map::cli::RenderArgs::parse(&args[1..])
+            .map_err(|e| format!("{}\n{}", e, tes3map::cli::RENDER_USAGE))
+            .and_then(|render_args| {
+                tes3map::cli::render(&render_args).map_err(|e| format!("{}", e))
+            });
+        if let Err(e) = result {
+            error!("{}", e);
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([800.0, 600.0])
@@ -31,3 +50,19 @@ fn main() -> eframe::Result<()> {
         Box::new(|cc| Ok(Box::new(tes3map::TemplateApp::new(cc)))),
     )
 }
+
+/// Release builds have no console of their own, print render errors to the console of the
+/// shell that started the process
+#[cfg(windows)]
+fn attach_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    // fails if the process already has a console, e.g. in debug builds
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
@@ -0,0 +1,182 @@
+use egui::{emath::RectTransform, epaint::Fonts, pos2, Align2, FontId, Pos2, Rect, Shape, Stroke};
+
+use crate::{dimensions::Dimensions, get_rect_at_cell, ContourSettings, VERTEX_CNT};
+
+/// Smallest distance in pixels between two traced vertices
+const MIN_VERTEX_SPACING: f32 = 4.0;
+/// Height labels are only placed in cells at least this many pixels wide
+const MIN_LABEL_CELL_SIZE: f32 = 128.0;
+
+/// Corners of a square crossed by a contour edge, top, right, bottom, left
+const EDGES: [(usize, usize); 4] = [(0, 1), (1, 2), (2, 3), (3, 0)];
+
+/// Pairs of crossed edges of a marching squares case, corners above the level are
+/// bit 1 top left, 2 top right, 4 bottom right, 8 bottom left.
+/// The saddles 5 and 10 are resolved with the center of the square
+fn get_case_edges(case: usize, center_above: bool) -> &'static [(usize, usize)] {
+    match case {
+        1 | 14 => &[(3, 0)],
+        2 | 13 => &[(0, 1)],
+        3 | 12 => &[(3, 1)],
+        4 | 11 => &[(1, 2)],
+        6 | 9 => &[(0, 2)],
+        7 | 8 => &[(3, 2)],
+        5 if center_above => &[(0, 1), (2, 3)],
+        5 => &[(3, 0), (1, 2)],
+        10 if center_above => &[(3, 0), (1, 2)],
+        10 => &[(0, 1), (2, 3)],
+        _ => &[],
+    }
+}
+
+/// Iso-height lines traced with marching squares through the vertex heights of the cells inside clip.
+/// Every index_every-th line is thicker and can be labeled with its height.
+/// The coastline is the line at height 0, which the contour lines leave out.
+/// Fewer vertices are traced the smaller the cells are on screen
+pub fn get_contour_shapes(
+    to_screen: RectTransform,
+    clip: Rect,
+    dimensions: &Dimensions,
+    heights: &[f32],
+    settings: &ContourSettings,
+    coastline: bool,
+    fonts: &Fonts,
+) -> Vec<Shape> {
+    let mut shapes = vec![];
+    if heights.is_empty() {
+        return shapes;
+    }
+
+    let cell_size = to_screen.scale().x;
+    let last = VERTEX_CNT - 1;
+    let step = ((MIN_VERTEX_SPACING * last as f32 / cell_size)
+        .ceil()
+        .max(1.0) as usize)
+        .next_power_of_two()
+        .min(last);
+    let interval = settings.interval.max(8.0);
+    let index_every = settings.index_every.max(1) as i64;
+
+    let stroke = Stroke::new(settings.width, settings.color);
+    let index_stroke = Stroke::new(settings.index_width, settings.color);
+    let coastline_stroke = Stroke::new(settings.coastline_width, settings.coastline_color);
+
+    let label_font = FontId::proportional(settings.label_size);
+    let labels = !coastline && settings.labels && cell_size >= MIN_LABEL_CELL_SIZE;
+    let mut placed: Vec<Rect> = vec![];
+
+    let stride = dimensions.stride(VERTEX_CNT);
+    for cy in dimensions.min_y..=dimensions.max_y {
+        for cx in dimensions.min_x..=dimensions.max_x {
+            let cell_rect = get_rect_at_cell(dimensions, to_screen, (cx, cy));
+            if !clip.intersects(cell_rect) {
+                continue;
+            }
+
+            let tx0 = dimensions.tranform_to_canvas_x(cx) * VERTEX_CNT;
+            let ty0 = dimensions.tranform_to_canvas_y(cy) * VERTEX_CNT;
+            let get = |x: usize, y: usize| {
+                heights
+                    .get((ty0 + y) * stride + tx0 + x)
+                    .copied()
+                    .filter(|z| *z >= dimensions.min_z)
+            };
+            let to_pos = |x: f32, y: f32| {
+                pos2(
+                    cell_rect.min.x + x / last as f32 * cell_rect.width(),
+                    cell_rect.min.y + y / last as f32 * cell_rect.height(),
+                )
+            };
+
+            // one label per index line and cell
+            let mut labeled: Vec<i64> = vec![];
+
+            for y in (0..last).step_by(step) {
+                for x in (0..last).step_by(step) {
+                    let corners = [(x, y), (x + step, y), (x + step, y + step), (x, y + step)];
+                    let (Some(a), Some(b), Some(c), Some(d)) = (
+                        get(corners[0].0, corners[0].1),
+                        get(corners[1].0, corners[1].1),
+                        get(corners[2].0, corners[2].1),
+                        get(corners[3].0, corners[3].1),
+                    ) else {
+                        continue;
+                    };
+                    let values = [a, b, c, d];
+
+                    let min = values.iter().copied().fold(f32::MAX, f32::min);
+                    let max = values.iter().copied().fold(f32::MIN, f32::max);
+                    let (first, end) = if coastline {
+                        (0, 0)
+                    } else {
+                        (
+                            (min / interval).ceil() as i64,
+                            (max / interval).floor() as i64,
+                        )
+                    };
+                    for level_index in first..=end {
+                        if !coastline && level_index == 0 {
+                            continue;
+                        }
+                        let level = level_index as f32 * interval;
+                        if level < min || level > max || min == max {
+                            continue;
+                        }
+
+                        let case = values
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, v)| **v >= level)
+                            .fold(0, |case, (i, _)| case | 1 << i);
+                        let center_above = (a + b + c + d) / 4.0 >= level;
+
+                        let crossing = |edge: usize| -> Pos2 {
+                            let (p, q) = EDGES[edge];
+                            let t = ((level - values[p]) / (values[q] - values[p])).clamp(0.0, 1.0);
+                            let (px, py) = corners[p];
+                            let (qx, qy) = corners[q];
+                            to_pos(
+                                px as f32 + (qx as f32 - px as f32) * t,
+                                py as f32 + (qy as f32 - py as f32) * t,
+                            )
+                        };
+
+                        let is_index = level_index % index_every == 0;
+                        let stroke = match (coastline, is_index) {
+                            (true, _) => coastline_stroke,
+                            (false, true) => index_stroke,
+                            (false, false) => stroke,
+                        };
+                        for (from, to) in get_case_edges(case, center_above) {
+                            let points = [crossing(*from), crossing(*to)];
+                            shapes.push(Shape::line_segment(points, stroke));
+
+                            if labels && is_index && !labeled.contains(&level_index) {
+                                let galley = fonts.layout_no_wrap(
+                                    format!("{}", level as i32),
+                                    label_font.clone(),
+                                    settings.color,
+                                );
+                                let center = points[0] + (points[1] - points[0]) / 2.0;
+                                let rect = Align2::CENTER_CENTER
+                                    .anchor_size(center, galley.size())
+                                    .expand(2.0);
+                                if !placed.iter().any(|r| r.intersects(rect)) {
+                                    placed.push(rect);
+                                    labeled.push(level_index);
+                                    shapes.push(Shape::galley(
+                                        rect.shrink(2.0).min,
+                                        galley,
+                                        settings.color,
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    shapes
+}
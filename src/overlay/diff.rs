@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use egui::{emath::RectTransform, Color32, Rounding, Shape};
+
+use crate::diff::CellDiff;
+use crate::dimensions::Dimensions;
+use crate::{get_rect_at_cell, CellKey};
+
+pub fn get_diff_color(diff: &CellDiff) -> Color32 {
+    if diff.added {
+        Color32::from_rgba_unmultiplied(0, 255, 0, 100)
+    } else if diff.removed {
+        Color32::from_rgba_unmultiplied(255, 0, 0, 100)
+    } else if diff.heights_changed > 0
+        || diff.vertex_colors_changed > 0
+        || diff.textures_changed > 0
+    {
+        // landscape edits
+        Color32::from_rgba_unmultiplied(255, 140, 0, 100)
+    } else {
+        // cell edits only
+        Color32::from_rgba_unmultiplied(255, 255, 0, 100)
+    }
+}
+
+pub fn get_diff_shapes(
+    to_screen: RectTransform,
+    dimensions: &Dimensions,
+    cell_diffs: &HashMap<CellKey, CellDiff>,
+) -> Vec<Shape> {
+    let mut shapes: Vec<Shape> = Vec::with_capacity(cell_diffs.len());
+
+    for (key, diff) in cell_diffs {
+        // check that key is within the dimensions
+        if key.0 < dimensions.min_x
+            || key.0 > dimensions.max_x
+            || key.1 < dimensions.min_y
+            || key.1 > dimensions.max_y
+        {
+            continue;
+        }
+
+        let rect = get_rect_at_cell(dimensions, to_screen, *key);
+        let shape = Shape::rect_filled(rect, Rounding::default(), get_diff_color(diff));
+        shapes.push(shape);
+    }
+
+    shapes
+}
@@ -0,0 +1,47 @@
+use egui::{emath::RectTransform, Color32, Rect, Shape, Stroke};
+
+use crate::dimensions::Dimensions;
+use crate::doors::{DoorConnection, DoorEntrance};
+use crate::get_pos_at_translation;
+
+pub fn get_door_shapes(
+    to_screen: RectTransform,
+    dimensions: &Dimensions,
+    entrances: &[DoorEntrance],
+    connections: &[DoorConnection],
+) -> Vec<Shape> {
+    let mut shapes: Vec<Shape> = Vec::with_capacity(entrances.len() + connections.len() * 2);
+    let inside = |key: (i32, i32)| {
+        key.0 >= dimensions.min_x
+            && key.0 <= dimensions.max_x
+            && key.1 >= dimensions.min_y
+            && key.1 <= dimensions.max_y
+    };
+
+    // doors into interiors
+    for entrance in entrances {
+        if !inside(entrance.cell) {
+            continue;
+        }
+        let center = get_pos_at_translation(dimensions, to_screen, entrance.translation);
+        shapes.push(Shape::rect_filled(
+            Rect::from_center_size(center, egui::vec2(5.0, 5.0)),
+            0.0,
+            Color32::from_rgb(200, 120, 40),
+        ));
+    }
+
+    // exits in other exterior cells
+    let color = Color32::from_rgb(255, 0, 255);
+    for connection in connections {
+        if !inside(connection.from_cell()) && !inside(connection.to_cell()) {
+            continue;
+        }
+        let from = get_pos_at_translation(dimensions, to_screen, connection.from);
+        let to = get_pos_at_translation(dimensions, to_screen, connection.to);
+        shapes.push(Shape::line_segment([from, to], Stroke::new(2.0, color)));
+        shapes.push(Shape::circle_filled(to, 3.0, color));
+    }
+
+    shapes
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet};
+
+use egui::{emath::RectTransform, epaint::Fonts, vec2, FontId, Rect, Shape, Vec2};
+use tes3::esp::Cell;
+
+use crate::overlay::regions::get_label_shapes;
+use crate::{dimensions::Dimensions, get_rect_at_cell, CellKey, LabelSettings};
+
+/// Named exterior cells grouped with their adjacent (also diagonal) cells of the same name
+fn get_named_groups(
+    dimensions: &Dimensions,
+    cell_records: &HashMap<CellKey, Cell>,
+) -> Vec<(String, Vec<CellKey>)> {
+    let name_of = |key: &CellKey| {
+        cell_records
+            .get(key)
+            .map(|c| c.name.as_str())
+            .filter(|n| !n.is_empty())
+    };
+
+    let mut keys = cell_records
+        .keys()
+        .filter(|k| {
+            k.0 >= dimensions.min_x
+                && k.0 <= dimensions.max_x
+                && k.1 >= dimensions.min_y
+                && k.1 <= dimensions.max_y
+        })
+        .filter(|k| name_of(k).is_some())
+        .copied()
+        .collect::<Vec<_>>();
+    keys.sort();
+
+    let mut visited = HashSet::new();
+    let mut groups = vec![];
+    for key in keys {
+        if !visited.insert(key) {
+            continue;
+        }
+        let name = name_of(&key).unwrap_or_default().to_owned();
+
+        // flood fill
+        let mut group = vec![key];
+        let mut i = 0;
+        while i < group.len() {
+            let (x, y) = group[i];
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let neighbor = (x + dx, y + dy);
+                    if name_of(&neighbor).is_some_and(|n| n.eq_ignore_ascii_case(&name))
+                        && visited.insert(neighbor)
+                    {
+                        group.push(neighbor);
+                    }
+                }
+            }
+            i += 1;
+        }
+        groups.push((name, group));
+    }
+    groups
+}
+
+/// One label per group of adjacent cells with the same name, at the center of the group.
+/// Larger groups are placed first, labels that would overlap a placed one are moved
+/// above, below or beside their position or left out
+pub fn get_place_label_shapes(
+    to_screen: RectTransform,
+    dimensions: &Dimensions,
+    cell_records: &HashMap<CellKey, Cell>,
+    settings: &LabelSettings,
+    fonts: &Fonts,
+) -> Vec<Shape> {
+    let mut groups = get_named_groups(dimensions, cell_records);
+    groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0)));
+
+    // font size follows the size of a cell on screen
+    let cell_size = to_screen.scale().x;
+    let mut placed: Vec<Rect> = vec![];
+    let mut shapes = vec![];
+
+    for (name, cells) in groups {
+        let center = cells.iter().fold(Vec2::ZERO, |sum, key| {
+            sum + get_rect_at_cell(dimensions, to_screen, *key)
+                .center()
+                .to_vec2()
+        }) / cells.len() as f32;
+
+        let group_scale = (1.0 + 0.1 * (cells.len() - 1) as f32).min(1.5);
+        let size = (cell_size * settings.scale * group_scale)
+            .clamp(settings.min_size, settings.max_size.max(settings.min_size));
+        let text_size = fonts
+            .layout_no_wrap(name.clone(), FontId::proportional(size), settings.color)
+            .size();
+
+        let candidates = [
+            vec2(0.0, 0.0),
+            vec2(0.0, -text_size.y),
+            vec2(0.0, text_size.y),
+            vec2(text_size.x * 0.6, 0.0),
+            vec2(-text_size.x * 0.6, 0.0),
+        ];
+        let Some(rect) = candidates
+            .iter()
+            .map(|offset| Rect::from_center_size(center.to_pos2() + *offset, text_size))
+            .find(|rect| !placed.iter().any(|p| p.intersects(*rect)))
+        else {
+            continue;
+        };
+        placed.push(rect.expand(2.0));
+
+        shapes.extend(get_label_shapes(
+            fonts,
+            rect.center(),
+            &name,
+            size,
+            settings.color,
+        ));
+    }
+
+    shapes
+}
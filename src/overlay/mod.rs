@@ -1,7 +1,16 @@
 pub mod cities;
 pub mod conflicts;
+pub mod contours;
+pub mod diff;
+pub mod doors;
 pub mod grid;
 pub mod paths;
+pub mod references;
 pub mod regions;
+pub mod route;
+pub mod seams;
 pub mod travel;
+pub mod weather;
+pub mod wnam;
+pub mod labels;
 pub mod interventions;
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use egui::{emath::RectTransform, Pos2, Rect, Shape};
+use tes3::esp::Cell;
+
+use crate::dimensions::Dimensions;
+use crate::interior::{get_object_type, get_placed_references, EObjectType};
+use crate::{get_pos_at_translation, get_rect_at_cell, CellKey, ReferenceFilter};
+
+/// Reference markers of the cells inside clip
+pub fn get_reference_shapes(
+    to_screen: RectTransform,
+    clip: Rect,
+    dimensions: &Dimensions,
+    cell_records: &HashMap<CellKey, Cell>,
+    object_types: &HashMap<String, EObjectType>,
+    filter: &ReferenceFilter,
+) -> Vec<Shape> {
+    let mut shapes: Vec<Shape> = vec![];
+
+    for (key, cell) in cell_records {
+        // check that key is within the dimensions
+        if key.0 < dimensions.min_x
+            || key.0 > dimensions.max_x
+            || key.1 < dimensions.min_y
+            || key.1 > dimensions.max_y
+        {
+            continue;
+        }
+
+        // references can be placed slightly outside of their cell
+        let rect = get_rect_at_cell(dimensions, to_screen, *key);
+        if !clip.intersects(rect.expand(rect.width() * 0.5)) {
+            continue;
+        }
+
+        for reference in get_placed_references(cell) {
+            let object_type = get_object_type(object_types, &reference.id);
+            if !filter.matches(object_type, &reference.id) {
+                continue;
+            }
+
+            let center = get_pos_at_translation(dimensions, to_screen, reference.translation);
+            shapes.push(Shape::circle_filled(
+                center,
+                2.5,
+                object_type.color().to_opaque(),
+            ));
+        }
+    }
+
+    shapes
+}
+
+/// The drawn reference closest to a screen position, as cell and reference key
+pub fn get_reference_at(
+    to_screen: RectTransform,
+    dimensions: &Dimensions,
+    cell_records: &HashMap<CellKey, Cell>,
+    object_types: &HashMap<String, EObjectType>,
+    filter: &ReferenceFilter,
+    key: CellKey,
+    pos: Pos2,
+) -> Option<(CellKey, (u32, u32))> {
+    let mut closest = None;
+    let mut closest_distance = 6.0;
+
+    // references can be placed slightly outside of their cell
+    for x in key.0 - 1..=key.0 + 1 {
+        for y in key.1 - 1..=key.1 + 1 {
+            let Some(cell) = cell_records.get(&(x, y)) else {
+                continue;
+            };
+            for (reference_key, reference) in &cell.references {
+                if reference.deleted.is_some()
+                    || !filter.matches(get_object_type(object_types, &reference.id), &reference.id)
+                {
+                    continue;
+                }
+
+                let center = get_pos_at_translation(dimensions, to_screen, reference.translation);
+                let distance = center.distance(pos);
+                if distance < closest_distance {
+                    closest_distance = distance;
+                    closest = Some(((x, y), *reference_key));
+                }
+            }
+        }
+    }
+
+    closest
+}
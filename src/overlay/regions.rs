@@ -1,40 +1,186 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use egui::{emath::RectTransform, Color32, Rounding, Shape};
+use egui::{
+    emath::RectTransform, epaint::Fonts, vec2, Align2, Color32, FontId, Pos2, Rounding, Shape,
+    Stroke, Vec2,
+};
 use tes3::esp::{Cell, Region};
 
-use crate::{dimensions::Dimensions, get_rect_at_cell, CellKey};
+use crate::region::get_region;
+use crate::{dimensions::Dimensions, get_rect_at_cell, CellKey, RegionStyle};
 
+/// Region colors with the style's opacity, borders where the region changes between
+/// neighboring cells and the region names at the centroid of their cells
 pub fn get_region_shapes(
     to_screen: RectTransform,
     dimensions: &Dimensions,
     regn_records: &HashMap<String, Region>,
     cell_records: &HashMap<CellKey, Cell>,
+    style: &RegionStyle,
+    fonts: &Fonts,
 ) -> Vec<Shape> {
     let shapes_len =
         (dimensions.max_x - dimensions.min_x + 1) * (dimensions.max_y - dimensions.min_y + 1);
     let mut shapes: Vec<Shape> = Vec::with_capacity(shapes_len as usize);
 
-    for cy in dimensions.min_y..dimensions.max_y + 1 {
-        for cx in dimensions.min_x..dimensions.max_x + 1 {
-            // get region
-            let key = (cx, cy);
-            if let Some(cell) = cell_records.get(&key) {
-                if let Some(region_name) = &cell.region {
-                    if let Some(region) = regn_records.get(region_name) {
-                        let region_color = Color32::from_rgb(
-                            region.map_color[0],
-                            region.map_color[1],
-                            region.map_color[2],
-                        );
+    let get_cell_region = |key: CellKey| {
+        cell_records
+            .get(&key)
+            .and_then(|cell| cell.region.as_ref())
+            .and_then(|id| get_region(regn_records, id))
+    };
 
-                        let rect = get_rect_at_cell(dimensions, to_screen, key);
-                        let shape = Shape::rect_filled(rect, Rounding::default(), region_color);
-                        shapes.push(shape);
+    // fill
+    if style.fill_opacity > 0.0 {
+        for cy in dimensions.min_y..dimensions.max_y + 1 {
+            for cx in dimensions.min_x..dimensions.max_x + 1 {
+                // get region
+                let key = (cx, cy);
+                if let Some(region) = get_cell_region(key) {
+                    let region_color = Color32::from_rgb(
+                        region.map_color[0],
+                        region.map_color[1],
+                        region.map_color[2],
+                    )
+                    .gamma_multiply(style.fill_opacity);
+
+                    let rect = get_rect_at_cell(dimensions, to_screen, key);
+                    let shape = Shape::rect_filled(rect, Rounding::default(), region_color);
+                    shapes.push(shape);
+                }
+            }
+        }
+    }
+
+    // borders, the edges to the east and north neighbor, starting outside to get the outer edges
+    if style.borders {
+        let stroke = Stroke::new(style.border_width, style.border_color);
+        // cells outside the dimensions have no rect of their own, they are clamped to the edge
+        let is_inside = |key: CellKey| {
+            key.0 >= dimensions.min_x
+                && key.0 <= dimensions.max_x
+                && key.1 >= dimensions.min_y
+                && key.1 <= dimensions.max_y
+        };
+        for cy in dimensions.min_y - 1..dimensions.max_y + 1 {
+            for cx in dimensions.min_x - 1..dimensions.max_x + 1 {
+                let key = (cx, cy);
+                let region = get_cell_region(key).map(|r| &r.id);
+                for neighbor in [(cx + 1, cy), (cx, cy + 1)] {
+                    let other = get_cell_region(neighbor).map(|r| &r.id);
+                    if region == other {
+                        continue;
                     }
+                    // north is up on the map
+                    let is_east = neighbor.0 != cx;
+                    let points = if is_inside(key) {
+                        let rect = get_rect_at_cell(dimensions, to_screen, key);
+                        if is_east {
+                            [rect.right_top(), rect.right_bottom()]
+                        } else {
+                            [rect.left_top(), rect.right_top()]
+                        }
+                    } else if is_inside(neighbor) {
+                        let rect = get_rect_at_cell(dimensions, to_screen, neighbor);
+                        if is_east {
+                            [rect.left_top(), rect.left_bottom()]
+                        } else {
+                            [rect.left_bottom(), rect.right_bottom()]
+                        }
+                    } else {
+                        continue;
+                    };
+                    shapes.push(Shape::line_segment(points, stroke));
                 }
             }
         }
     }
+
+    // labels
+    if style.labels {
+        let mut centroids: HashMap<&String, (Vec2, f32)> = HashMap::default();
+        for cy in dimensions.min_y..dimensions.max_y + 1 {
+            for cx in dimensions.min_x..dimensions.max_x + 1 {
+                if let Some(region) = get_cell_region((cx, cy)) {
+                    let center = get_rect_at_cell(dimensions, to_screen, (cx, cy)).center();
+                    let (sum, count) = centroids.entry(&region.id).or_insert((Vec2::ZERO, 0.0));
+                    *sum += center.to_vec2();
+                    *count += 1.0;
+                }
+            }
+        }
+
+        let mut ids = centroids.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        for id in ids {
+            let (sum, count) = centroids[id];
+            let name = match regn_records.get(id) {
+                Some(region) if !region.name.is_empty() => &region.name,
+                _ => id,
+            };
+            shapes.extend(get_label_shapes(
+                fonts,
+                (sum / count).to_pos2(),
+                name,
+                style.label_size,
+                style.label_color,
+            ));
+        }
+    }
+
+    shapes
+}
+
+/// Centered text with a dark shadow, readable on any background
+pub fn get_label_shapes(
+    fonts: &Fonts,
+    pos: Pos2,
+    text: &str,
+    size: f32,
+    color: Color32,
+) -> [Shape; 2] {
+    let font = FontId::proportional(size);
+    let shadow = Color32::from_black_alpha(200);
+    [
+        Shape::text(
+            fonts,
+            pos + vec2(1.0, 1.0),
+            Align2::CENTER_CENTER,
+            text,
+            font.clone(),
+            shadow,
+        ),
+        Shape::text(fonts, pos, Align2::CENTER_CENTER, text, font, color),
+    ]
+}
+
+/// Outline of a group of cells, drawn only where a cell's neighbor is not in the group
+pub fn get_outline_shapes(
+    to_screen: RectTransform,
+    dimensions: &Dimensions,
+    cells: &[CellKey],
+    stroke: Stroke,
+) -> Vec<Shape> {
+    let set = cells.iter().copied().collect::<HashSet<_>>();
+    let mut shapes: Vec<Shape> = vec![];
+
+    for key in cells {
+        let rect = get_rect_at_cell(dimensions, to_screen, *key);
+        // north is up on the map
+        let edges = [
+            ((key.0, key.1 + 1), [rect.left_top(), rect.right_top()]),
+            (
+                (key.0, key.1 - 1),
+                [rect.left_bottom(), rect.right_bottom()],
+            ),
+            ((key.0 - 1, key.1), [rect.left_top(), rect.left_bottom()]),
+            ((key.0 + 1, key.1), [rect.right_top(), rect.right_bottom()]),
+        ];
+        for (neighbor, points) in edges {
+            if !set.contains(&neighbor) {
+                shapes.push(Shape::line_segment(points, stroke));
+            }
+        }
+    }
     shapes
 }
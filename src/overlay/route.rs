@@ -0,0 +1,35 @@
+use egui::{emath::RectTransform, Color32, Shape, Stroke};
+
+use crate::dimensions::Dimensions;
+use crate::get_pos_at_translation;
+use crate::planner::RouteStep;
+
+/// Legs of a planned route, spells as dashed lines, with the start green and the end red
+pub fn get_route_shapes(
+    to_screen: RectTransform,
+    dimensions: &Dimensions,
+    steps: &[RouteStep],
+) -> Vec<Shape> {
+    let mut shapes: Vec<Shape> = Vec::with_capacity(steps.len() + 2);
+
+    for step in steps {
+        let from = get_pos_at_translation(dimensions, to_screen, step.from);
+        let to = get_pos_at_translation(dimensions, to_screen, step.to);
+        let stroke = Stroke::new(3.0, step.kind.color());
+        if step.kind.is_teleport() {
+            shapes.extend(Shape::dashed_line(&[from, to], stroke, 8.0, 6.0));
+        } else {
+            shapes.push(Shape::line_segment([from, to], stroke));
+        }
+        shapes.push(Shape::circle_filled(to, 3.0, step.kind.color()));
+    }
+
+    if let (Some(first), Some(last)) = (steps.first(), steps.last()) {
+        let start = get_pos_at_translation(dimensions, to_screen, first.from);
+        let end = get_pos_at_translation(dimensions, to_screen, last.to);
+        shapes.push(Shape::circle_filled(start, 6.0, Color32::GREEN));
+        shapes.push(Shape::circle_filled(end, 6.0, Color32::RED));
+    }
+
+    shapes
+}
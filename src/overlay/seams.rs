@@ -0,0 +1,56 @@
+use egui::{emath::RectTransform, Color32, Shape, Stroke};
+
+use crate::dimensions::Dimensions;
+use crate::get_rect_at_cell;
+use crate::seams::Seam;
+
+/// Height gap in game units at which a seam is drawn fully red
+const MAX_DELTA: f32 = 256.0;
+
+/// Yellow for small height gaps to red for large ones, blue if only colors or normals differ
+pub fn get_seam_color(seam: &Seam) -> Color32 {
+    if seam.heights_mismatched == 0 {
+        return Color32::LIGHT_BLUE;
+    }
+    let t = (seam.max_height_delta / MAX_DELTA).clamp(0.0, 1.0);
+    Color32::from_rgb(255, (255.0 * (1.0 - t)) as u8, 0)
+}
+
+pub fn get_seams_shapes(
+    to_screen: RectTransform,
+    dimensions: &Dimensions,
+    seams: &[Seam],
+) -> Vec<Shape> {
+    let mut shapes: Vec<Shape> = Vec::with_capacity(seams.len());
+
+    for seam in seams {
+        let key = seam.cell;
+        // check that key is within the dimensions
+        if key.0 < dimensions.min_x
+            || key.0 > dimensions.max_x
+            || key.1 < dimensions.min_y
+            || key.1 > dimensions.max_y
+        {
+            continue;
+        }
+
+        // north is up on screen
+        let rect = get_rect_at_cell(dimensions, to_screen, key);
+        let points = if seam.is_east() {
+            [rect.right_top(), rect.right_bottom()]
+        } else {
+            [rect.left_top(), rect.right_top()]
+        };
+        let width = if seam.heights_mismatched == 0 {
+            1.0
+        } else {
+            3.0
+        };
+        shapes.push(Shape::line_segment(
+            points,
+            Stroke::new(width, get_seam_color(seam)),
+        ));
+    }
+
+    shapes
+}
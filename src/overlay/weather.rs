@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use egui::{emath::RectTransform, Rounding, Shape};
+use tes3::esp::{Cell, Region};
+
+use crate::region::{get_dominant_weather, get_region};
+use crate::{dimensions::Dimensions, get_rect_at_cell, CellKey};
+
+/// Cells colored by the most likely weather of their region
+pub fn get_weather_shapes(
+    to_screen: RectTransform,
+    dimensions: &Dimensions,
+    regn_records: &HashMap<String, Region>,
+    cell_records: &HashMap<CellKey, Cell>,
+) -> Vec<Shape> {
+    let mut shapes: Vec<Shape> = Vec::with_capacity(cell_records.len());
+
+    for (key, cell) in cell_records {
+        if key.0 < dimensions.min_x
+            || key.0 > dimensions.max_x
+            || key.1 < dimensions.min_y
+            || key.1 > dimensions.max_y
+        {
+            continue;
+        }
+        let Some(weather) = cell
+            .region
+            .as_ref()
+            .and_then(|id| get_region(regn_records, id))
+            .and_then(get_dominant_weather)
+        else {
+            continue;
+        };
+
+        let rect = get_rect_at_cell(dimensions, to_screen, *key);
+        shapes.push(Shape::rect_filled(
+            rect,
+            Rounding::default(),
+            weather.color().gamma_multiply(0.6),
+        ));
+    }
+    shapes
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use egui::{emath::RectTransform, Color32, Rounding, Shape, Stroke};
+
+use crate::dimensions::Dimensions;
+use crate::{get_rect_at_cell, CellKey};
+
+/// Difference in world map height steps at which a cell is drawn fully red
+const MAX_DELTA: f32 = 16.0;
+
+/// Orange for world map heights slightly off to red for ones far from the vertex heights
+pub fn get_world_map_color(delta: u8, tolerance: u8) -> Color32 {
+    let t = ((delta - tolerance) as f32 / MAX_DELTA).clamp(0.0, 1.0);
+    Color32::from_rgb(255, (160.0 * (1.0 - t)) as u8, 0)
+}
+
+/// Cells whose stored world map heights (WNAM) are more than tolerance steps off their vertex heights
+pub fn get_world_map_shapes(
+    to_screen: RectTransform,
+    dimensions: &Dimensions,
+    world_map_deltas: &HashMap<CellKey, u8>,
+    tolerance: u8,
+) -> Vec<Shape> {
+    let mut shapes: Vec<Shape> = vec![];
+
+    for (key, delta) in world_map_deltas {
+        if *delta <= tolerance {
+            continue;
+        }
+        // check that key is within the dimensions
+        if key.0 < dimensions.min_x
+            || key.0 > dimensions.max_x
+            || key.1 < dimensions.min_y
+            || key.1 > dimensions.max_y
+        {
+            continue;
+        }
+
+        let rect = get_rect_at_cell(dimensions, to_screen, *key);
+        let color = get_world_map_color(*delta, tolerance);
+        shapes.push(Shape::rect_filled(
+            rect,
+            Rounding::default(),
+            color.gamma_multiply(0.3),
+        ));
+        shapes.push(Shape::rect_stroke(
+            rect.shrink(1.0),
+            Rounding::default(),
+            Stroke::new(2.0, color),
+        ));
+    }
+
+    shapes
+}
@@ -0,0 +1,263 @@
+use egui::Color32;
+use tes3::esp::Cell;
+
+use crate::*;
+
+/// Running speed in game units per second, an average character without speed buffs
+const WALK_SPEED: f32 = 300.0;
+/// Game seconds per real second, the default of the timescale global
+const TIME_SCALE: f32 = 30.0;
+/// Real seconds spent talking to a travel NPC or casting a spell, including the loading screen.
+/// Keeps routes from chaining pointless hops
+const JUMP_SECONDS: f32 = 10.0;
+
+/// The ways to get from one place to another
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EStepKind {
+    Walk,
+    Travel,
+    Almsivi,
+    Divine,
+    Kyne,
+    Recall,
+}
+
+impl EStepKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EStepKind::Walk => "Walk",
+            EStepKind::Travel => "Travel",
+            EStepKind::Almsivi => "Almsivi Intervention",
+            EStepKind::Divine => "Divine Intervention",
+            EStepKind::Kyne => "Kyne's Intervention",
+            EStepKind::Recall => "Recall",
+        }
+    }
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            EStepKind::Walk => Color32::WHITE,
+            EStepKind::Travel => Color32::from_rgb(255, 160, 0),
+            EStepKind::Almsivi => Color32::from_rgb(180, 25, 25),
+            EStepKind::Divine => Color32::from_gray(200),
+            EStepKind::Kyne => Color32::from_rgb(0, 160, 0),
+            EStepKind::Recall => Color32::from_rgb(160, 80, 255),
+        }
+    }
+
+    /// Spells move instantly, drawn as dashed lines
+    pub fn is_teleport(&self) -> bool {
+        !matches!(self, EStepKind::Walk | EStepKind::Travel)
+    }
+}
+
+/// One leg of a planned route
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteStep {
+    pub kind: EStepKind,
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+    /// estimated in-game hours
+    pub hours: f32,
+    pub description: String,
+}
+
+/// Estimated in-game hours to walk a distance in game units
+pub fn get_walk_hours(distance: f32) -> f32 {
+    distance / WALK_SPEED * TIME_SCALE / 3600.0
+}
+
+/// Format in-game hours as "2 h 15 min"
+pub fn format_hours(hours: f32) -> String {
+    let minutes = (hours * 60.0).round() as u32;
+    if minutes < 60 {
+        format!("{} min", minutes)
+    } else {
+        format!("{} h {} min", minutes / 60, minutes % 60)
+    }
+}
+
+/// Center of an exterior cell in game units
+fn get_cell_center(key: CellKey) -> [f32; 3] {
+    [
+        (key.0 as f32 + 0.5) * CELL_SIZE,
+        (key.1 as f32 + 0.5) * CELL_SIZE,
+        0.0,
+    ]
+}
+
+/// Position of the intervention marker in a cell, or the cell center
+fn get_marker_positions(interventions: &HashMap<CellKey, Cell>, marker: &str) -> Vec<[f32; 3]> {
+    let mut keys = interventions.keys().copied().collect::<Vec<_>>();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| {
+            interventions[&key]
+                .references
+                .values()
+                .find(|r| r.id.eq_ignore_ascii_case(marker))
+                .map(|r| r.translation)
+                .unwrap_or(get_cell_center(key))
+        })
+        .collect()
+}
+
+/// Index of the marker an intervention spell cast at a position takes the player to.
+/// "By Cell" compares the king's step distance between cells, ties broken by distance,
+/// "Pythagorean" the distance in game units
+fn get_nearest_marker(
+    markers: &[[f32; 3]],
+    position: [f32; 3],
+    intervention_engine: &str,
+) -> Option<usize> {
+    let cell = get_cell_at_translation(position);
+    let by_cell = intervention_engine != "Pythagorean";
+    (0..markers.len()).min_by(|a, b| {
+        let key = |m: [f32; 3]| {
+            let distance = get_distance(position, m);
+            if by_cell {
+                let marker_cell = get_cell_at_translation(m);
+                let steps = (marker_cell.0 - cell.0)
+                    .abs()
+                    .max((marker_cell.1 - cell.1).abs());
+                (steps as f32, distance)
+            } else {
+                (distance, 0.0)
+            }
+        };
+        key(markers[*a])
+            .partial_cmp(&key(markers[*b]))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })
+}
+
+/// The route between the centers of two exterior cells that takes the player the least real time.
+/// Walking legs are straight lines, travel services, interventions and Recall to a Mark
+/// in the mark cell cost a few seconds each. Steps list the in-game time they take
+pub fn plan_route(
+    world: &WorldData,
+    start: CellKey,
+    end: CellKey,
+    mark: Option<CellKey>,
+    intervention_engine: &str,
+) -> Vec<RouteStep> {
+    // nodes: start, end, both ends of every travel route
+    let mut nodes = vec![get_cell_center(start), get_cell_center(end)];
+    for route in &world.travel_routes {
+        nodes.push(route.from);
+        nodes.push(route.to);
+    }
+
+    let spells = [
+        (
+            EStepKind::Almsivi,
+            get_marker_positions(&world.almsivi_interventions, "TempleMarker"),
+        ),
+        (
+            EStepKind::Divine,
+            get_marker_positions(&world.divine_interventions, "DivineMarker"),
+        ),
+        (
+            EStepKind::Kyne,
+            get_marker_positions(&world.kyne_interventions, "T_Aid_KyneInterventionMarker"),
+        ),
+    ];
+    // index of the first marker node of each spell
+    let mut spell_nodes = vec![];
+    for (_, markers) in &spells {
+        spell_nodes.push(nodes.len());
+        nodes.extend(markers.iter().copied());
+    }
+    let mark_node = mark.map(|key| {
+        nodes.push(get_cell_center(key));
+        nodes.len() - 1
+    });
+
+    // dijkstra over the dense graph, every node can be walked to from every other
+    let count = nodes.len();
+    let mut seconds = vec![f32::INFINITY; count];
+    let mut previous: Vec<Option<(usize, EStepKind, Option<usize>)>> = vec![None; count];
+    let mut done = vec![false; count];
+    seconds[0] = 0.0;
+
+    while let Some(u) = (0..count)
+        .filter(|i| !done[*i] && seconds[*i].is_finite())
+        .min_by(|a, b| seconds[*a].total_cmp(&seconds[*b]))
+    {
+        done[u] = true;
+        if u == 1 {
+            break;
+        }
+
+        let mut relax = |v: usize, cost: f32, kind: EStepKind, route: Option<usize>| {
+            if !done[v] && seconds[u] + cost < seconds[v] {
+                seconds[v] = seconds[u] + cost;
+                previous[v] = Some((u, kind, route));
+            }
+        };
+
+        for v in 0..count {
+            relax(
+                v,
+                get_distance(nodes[u], nodes[v]) / WALK_SPEED,
+                EStepKind::Walk,
+                None,
+            );
+        }
+        // the travel routes start at node 2 + 2 * index
+        if u >= 2 && u < 2 + world.travel_routes.len() * 2 && (u - 2) % 2 == 0 {
+            let index = (u - 2) / 2;
+            relax(u + 1, JUMP_SECONDS, EStepKind::Travel, Some(index));
+        }
+        for ((kind, markers), first) in spells.iter().zip(spell_nodes.iter()) {
+            if let Some(i) = get_nearest_marker(markers, nodes[u], intervention_engine) {
+                relax(first + i, JUMP_SECONDS, *kind, None);
+            }
+        }
+        if let Some(v) = mark_node {
+            relax(v, JUMP_SECONDS, EStepKind::Recall, None);
+        }
+    }
+
+    // walk back from the end
+    let mut steps = vec![];
+    let mut v = 1;
+    while let Some((u, kind, route)) = previous[v] {
+        if kind == EStepKind::Walk && nodes[u] == nodes[v] {
+            v = u;
+            continue;
+        }
+        let to_name = get_cell_name(&world.cell_records, get_cell_at_translation(nodes[v]));
+        let route = route.map(|i| &world.travel_routes[i]);
+        let hours = match (kind, route) {
+            (EStepKind::Walk, _) => get_walk_hours(get_distance(nodes[u], nodes[v])),
+            (EStepKind::Travel, Some(route)) => route.hours,
+            _ => 0.0,
+        };
+        let description = match (kind, route) {
+            (EStepKind::Travel, Some(route)) => {
+                let npc = if route.name.is_empty() {
+                    &route.npc
+                } else {
+                    &route.name
+                };
+                let cost = route
+                    .cost
+                    .map(|c| format!(", {} gold", c))
+                    .unwrap_or_default();
+                format!("{} with {} to {}{}", route.class, npc, to_name, cost)
+            }
+            _ => format!("{} to {}", kind.label(), to_name),
+        };
+        steps.push(RouteStep {
+            kind,
+            from: nodes[u],
+            to: nodes[v],
+            hours,
+            description,
+        });
+        v = u;
+    }
+    steps.reverse();
+    steps
+}
@@ -0,0 +1,125 @@
+use egui::Color32;
+use tes3::esp::{Cell, Region, WeatherChances};
+
+use crate::*;
+
+/// Weather types of a region, in record order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EWeather {
+    Clear,
+    Cloudy,
+    Foggy,
+    Overcast,
+    Rain,
+    Thunder,
+    Ash,
+    Blight,
+    Snow,
+    Blizzard,
+}
+
+impl EWeather {
+    pub const ALL: [EWeather; 10] = [
+        EWeather::Clear,
+        EWeather::Cloudy,
+        EWeather::Foggy,
+        EWeather::Overcast,
+        EWeather::Rain,
+        EWeather::Thunder,
+        EWeather::Ash,
+        EWeather::Blight,
+        EWeather::Snow,
+        EWeather::Blizzard,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EWeather::Clear => "Clear",
+            EWeather::Cloudy => "Cloudy",
+            EWeather::Foggy => "Foggy",
+            EWeather::Overcast => "Overcast",
+            EWeather::Rain => "Rain",
+            EWeather::Thunder => "Thunder",
+            EWeather::Ash => "Ash",
+            EWeather::Blight => "Blight",
+            EWeather::Snow => "Snow",
+            EWeather::Blizzard => "Blizzard",
+        }
+    }
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            EWeather::Clear => Color32::from_rgb(255, 220, 80),
+            EWeather::Cloudy => Color32::from_rgb(170, 200, 230),
+            EWeather::Foggy => Color32::from_rgb(200, 200, 200),
+            EWeather::Overcast => Color32::from_rgb(120, 130, 150),
+            EWeather::Rain => Color32::from_rgb(40, 100, 220),
+            EWeather::Thunder => Color32::from_rgb(90, 40, 160),
+            EWeather::Ash => Color32::from_rgb(150, 90, 60),
+            EWeather::Blight => Color32::from_rgb(200, 40, 40),
+            EWeather::Snow => Color32::from_rgb(240, 250, 255),
+            EWeather::Blizzard => Color32::from_rgb(120, 230, 240),
+        }
+    }
+
+    /// Chance in percent of this weather in a region
+    pub fn chance(&self, chances: &WeatherChances) -> u8 {
+        match self {
+            EWeather::Clear => chances.clear,
+            EWeather::Cloudy => chances.cloudy,
+            EWeather::Foggy => chances.foggy,
+            EWeather::Overcast => chances.overcast,
+            EWeather::Rain => chances.rain,
+            EWeather::Thunder => chances.thunder,
+            EWeather::Ash => chances.ash,
+            EWeather::Blight => chances.blight,
+            EWeather::Snow => chances.snow,
+            EWeather::Blizzard => chances.blizzard,
+        }
+    }
+}
+
+/// Weather types with a chance above zero, most likely first
+pub fn get_weather_chances(region: &Region) -> Vec<(EWeather, u8)> {
+    let mut chances = EWeather::ALL
+        .iter()
+        .map(|w| (*w, w.chance(&region.weather_chances)))
+        .filter(|(_, chance)| *chance > 0)
+        .collect::<Vec<_>>();
+    chances.sort_by_key(|(_, chance)| std::cmp::Reverse(*chance));
+    chances
+}
+
+/// The most likely weather of a region, the first in record order on ties
+pub fn get_dominant_weather(region: &Region) -> Option<EWeather> {
+    let chances = get_weather_chances(region);
+    let max = chances.first()?.1;
+    EWeather::ALL
+        .into_iter()
+        .find(|w| w.chance(&region.weather_chances) == max)
+}
+
+/// The region record of a cell's region id, which the game matches case insensitive
+pub fn get_region<'a>(regn_records: &'a HashMap<String, Region>, id: &str) -> Option<&'a Region> {
+    regn_records.get(id).or_else(|| {
+        regn_records
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(id))
+            .map(|(_, region)| region)
+    })
+}
+
+/// All exterior cells of a region, sorted
+pub fn get_region_cells(cell_records: &HashMap<CellKey, Cell>, id: &str) -> Vec<CellKey> {
+    let mut keys = cell_records
+        .iter()
+        .filter(|(_, cell)| {
+            cell.region
+                .as_ref()
+                .is_some_and(|region| region.eq_ignore_ascii_case(id))
+        })
+        .map(|(key, _)| *key)
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys
+}
@@ -0,0 +1,152 @@
+use serde::Serialize;
+use tes3::esp::{Landscape, LandscapeFlags};
+
+use crate::conflicts::PluginEdit;
+use crate::*;
+
+/// A border between two adjacent landscapes whose shared vertices don't match
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Seam {
+    /// the west or south cell
+    pub cell: CellKey,
+    /// the east or north cell
+    pub neighbor: CellKey,
+    /// the plugins whose LAND records win for cell and neighbor
+    pub plugins: (String, String),
+
+    /// number of mismatched vertices and the largest height gap, in game units
+    pub heights_mismatched: usize,
+    pub max_height_delta: f32,
+    pub vertex_colors_mismatched: usize,
+    pub normals_mismatched: usize,
+}
+
+impl Seam {
+    /// The seam is vertical on the map
+    pub fn is_east(&self) -> bool {
+        self.neighbor.0 != self.cell.0
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{:?} {} / {:?} {}: max delta {}, {} heights, {} vertex colors, {} normals",
+            self.cell,
+            self.plugins.0,
+            self.neighbor,
+            self.plugins.1,
+            self.max_height_delta,
+            self.heights_mismatched,
+            self.vertex_colors_mismatched,
+            self.normals_mismatched
+        )
+    }
+}
+
+/// JSON report of all seams
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SeamsReport {
+    pub plugins: Vec<String>,
+    pub seams: Vec<Seam>,
+}
+
+impl SeamsReport {
+    pub fn new(world: &WorldData) -> Self {
+        Self {
+            plugins: world.plugins.iter().map(|p| p.get_name()).collect(),
+            seams: world.seams.clone(),
+        }
+    }
+}
+
+/// Shared border vertices of a landscape, rows from south to north
+struct Border {
+    heights: [f32; VERTEX_CNT],
+    colors: [[u8; 3]; VERTEX_CNT],
+    normals: [[i8; 3]; VERTEX_CNT],
+}
+
+impl Border {
+    /// The row or column at index 0 or 64 of a landscape
+    fn new(land: &Landscape, column: bool, index: usize) -> Option<Self> {
+        let heights = get_landscape_heights(land)?;
+        let uses_colors = land
+            .landscape_flags
+            .contains(LandscapeFlags::USES_VERTEX_COLORS);
+
+        let mut border = Border {
+            heights: [0.0; VERTEX_CNT],
+            colors: [[255; 3]; VERTEX_CNT],
+            normals: [[0; 3]; VERTEX_CNT],
+        };
+        for i in 0..VERTEX_CNT {
+            let (y, x) = if column { (i, index) } else { (index, i) };
+            border.heights[i] = heights[y][x];
+            if uses_colors {
+                border.colors[i] = land.vertex_colors.data[y][x];
+            }
+            border.normals[i] = land.vertex_normals.data[y][x];
+        }
+        Some(border)
+    }
+}
+
+/// Compare the east and north border of every landscape with its neighbor
+pub fn find_seams(
+    land_records: &HashMap<CellKey, Landscape>,
+    cell_edits: &HashMap<CellKey, Vec<PluginEdit>>,
+    plugins: &[PluginViewModel],
+) -> Vec<Seam> {
+    let owner = |key: &CellKey| {
+        cell_edits
+            .get(key)
+            .and_then(|edits| edits.iter().rev().find(|e| e.land))
+            .and_then(|edit| plugins.iter().find(|p| p.hash == edit.plugin))
+            .map(|p| p.get_name())
+            .unwrap_or_default()
+    };
+
+    let mut keys = land_records.keys().copied().collect::<Vec<_>>();
+    keys.sort();
+
+    let mut seams = vec![];
+    for key in keys {
+        let land = &land_records[&key];
+        for (neighbor, column) in [((key.0 + 1, key.1), true), ((key.0, key.1 + 1), false)] {
+            let Some(other) = land_records.get(&neighbor) else {
+                continue;
+            };
+            let (Some(a), Some(b)) = (
+                Border::new(land, column, VERTEX_CNT - 1),
+                Border::new(other, column, 0),
+            ) else {
+                continue;
+            };
+
+            let mut seam = Seam {
+                cell: key,
+                neighbor,
+                ..Default::default()
+            };
+            for i in 0..VERTEX_CNT {
+                let delta = (a.heights[i] - b.heights[i]).abs();
+                if delta > 0.0 {
+                    seam.heights_mismatched += 1;
+                    seam.max_height_delta = seam.max_height_delta.max(delta);
+                }
+                if a.colors[i] != b.colors[i] {
+                    seam.vertex_colors_mismatched += 1;
+                }
+                if a.normals[i] != b.normals[i] {
+                    seam.normals_mismatched += 1;
+                }
+            }
+
+            if seam.heights_mismatched + seam.vertex_colors_mismatched + seam.normals_mismatched > 0
+            {
+                seam.plugins = (owner(&key), owner(&neighbor));
+                seams.push(seam);
+            }
+        }
+    }
+    seams
+}
@@ -0,0 +1,496 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use egui::{emath::RectTransform, pos2, Color32, ColorImage, Painter, Rect};
+use log::debug;
+use tes3::esp::{Landscape, LandscapeTexture};
+
+use crate::background::{
+    heightmap::generate_heightmap,
+    landscape::{compute_landscape_image, load_texture_map},
+    terrain::{blend_hillshade, generate_aspect_map, generate_hillshade, generate_slope_map},
+};
+use crate::*;
+
+/// Cells per tile side
+pub const TILE_CELLS: i32 = 8;
+
+/// Tile index, cell grid divided by TILE_CELLS
+pub type TileKey = (i32, i32);
+
+/// Pixels per cell to render tiles at, for the given screen pixels per cell
+fn get_lod(
+    background: EBackground,
+    settings: &SavedData,
+    pixels_per_cell: f32,
+    max_texture_side: usize,
+) -> usize {
+    let max_lod = max_texture_side / TILE_CELLS as usize;
+    match background {
+        EBackground::Landscape => {
+            // whole texture tiles, up to the texture resolution setting
+            let texture_size = (pixels_per_cell / GRID_SIZE as f32).ceil().max(1.0) as usize;
+            let max_texture_size = settings
+                .landscape_settings
+                .texture_size
+                .min(max_lod / GRID_SIZE)
+                .max(1);
+            texture_size.next_power_of_two().min(max_texture_size) * GRID_SIZE
+        }
+        _ => (pixels_per_cell.ceil().max(1.0) as usize)
+            .next_power_of_two()
+            .min(VERTEX_CNT)
+            .min(max_lod),
+    }
+}
+
+struct Tile {
+    lod: usize,
+    handle: egui::TextureHandle,
+}
+
+struct TileJob {
+    generation: u64,
+    key: TileKey,
+    lod: usize,
+    dimensions: Dimensions,
+    land_records: HashMap<CellKey, Landscape>,
+    /// the tile and one cell around it, so the terrain is shaded across the tile edges
+    padded: Dimensions,
+    heights: Vec<f32>,
+    settings: SavedData,
+}
+
+enum TileMessage {
+    /// new plugins or data files, drops all loaded textures
+    Reset {
+        vfs: Vfs,
+        ltex_records: HashMap<u32, LandscapeTexture>,
+    },
+    Render(Box<TileJob>),
+}
+
+struct TileResult {
+    generation: u64,
+    key: TileKey,
+    lod: usize,
+    /// None if the job was skipped
+    image: Option<ColorImage>,
+}
+
+/// Landscape and heightmap backgrounds split into tiles of TILE_CELLS cells.
+/// Visible tiles are rendered on a worker thread at the resolution needed for the current zoom
+#[derive(Default)]
+pub struct TileCache {
+    /// settings the tiles are rendered with
+    settings: SavedData,
+    tiles: HashMap<TileKey, Tile>,
+    pending: HashSet<(TileKey, usize)>,
+    needs_reset: bool,
+
+    generation: Arc<AtomicU64>,
+    lod: Arc<AtomicUsize>,
+    sender: Option<Sender<TileMessage>>,
+    receiver: Option<Receiver<TileResult>>,
+}
+
+impl TileCache {
+    /// Drops all tiles, e.g. when the background settings changed
+    pub fn clear(&mut self, settings: &SavedData) {
+        self.settings = settings.clone();
+        self.tiles.clear();
+        self.pending.clear();
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Drops all tiles and textures, e.g. when new plugins were loaded
+    pub fn reset(&mut self, settings: &SavedData) {
+        self.clear(settings);
+        self.needs_reset = true;
+    }
+
+    /// Requests missing tiles for the visible part of the map and paints all loaded tiles
+    pub fn paint(
+        &mut self,
+        ctx: &egui::Context,
+        painter: &Painter,
+        world: &WorldData,
+        vfs: &Vfs,
+        to_screen: RectTransform,
+    ) {
+        let background = self.settings.background;
+        if !background.is_tiled() || world.heights.is_empty() {
+            return;
+        }
+
+        self.receive(ctx);
+
+        let max_texture_side = ctx.input(|i| i.max_texture_side);
+        let pixels_per_cell = to_screen.scale().x;
+        let lod = get_lod(
+            background,
+            &self.settings,
+            pixels_per_cell,
+            max_texture_side,
+        );
+        self.lod.store(lod, Ordering::Relaxed);
+
+        let d = &world.dimensions;
+        let clip_rect = painter.clip_rect();
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        let mut visible = HashSet::new();
+        for ky in d.min_y.div_euclid(TILE_CELLS)..=d.max_y.div_euclid(TILE_CELLS) {
+            for kx in d.min_x.div_euclid(TILE_CELLS)..=d.max_x.div_euclid(TILE_CELLS) {
+                let key = (kx, ky);
+                let tile_dimensions = get_tile_dimensions(d, key);
+                let rect = get_tile_rect(d, &tile_dimensions, to_screen);
+                if !rect.intersects(clip_rect) {
+                    continue;
+                }
+                visible.insert(key);
+
+                let tile_lod = self.tiles.get(&key).map(|t| t.lod);
+                if tile_lod != Some(lod) && !self.pending.contains(&(key, lod)) {
+                    self.request(ctx, world, vfs, key, tile_dimensions, lod);
+                }
+
+                if let Some(tile) = self.tiles.get(&key) {
+                    painter.image(tile.handle.id(), rect, uv, Color32::WHITE);
+                }
+            }
+        }
+
+        // free detailed tiles that scrolled out of view
+        self.tiles
+            .retain(|key, tile| visible.contains(key) || tile.lod <= lod);
+    }
+
+    fn receive(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+
+        let generation = self.generation.load(Ordering::Relaxed);
+        while let Ok(result) = receiver.try_recv() {
+            if result.generation != generation {
+                continue;
+            }
+            self.pending.remove(&(result.key, result.lod));
+
+            if let Some(image) = result.image {
+                let name = format!("tile_{}_{}", result.key.0, result.key.1);
+                let handle = ctx.load_texture(name, image, Default::default());
+                self.tiles.insert(
+                    result.key,
+                    Tile {
+                        lod: result.lod,
+                        handle,
+                    },
+                );
+            }
+        }
+    }
+
+    fn request(
+        &mut self,
+        ctx: &egui::Context,
+        world: &WorldData,
+        vfs: &Vfs,
+        key: TileKey,
+        dimensions: Dimensions,
+        lod: usize,
+    ) {
+        if self.sender.is_none() {
+            let (sender, receiver) = self.spawn(ctx);
+            self.sender = Some(sender);
+            self.receiver = Some(receiver);
+            self.needs_reset = true;
+        }
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        if self.needs_reset {
+            self.needs_reset = false;
+            let _ = sender.send(TileMessage::Reset {
+                vfs: vfs.clone(),
+                ltex_records: world.ltex_records.clone(),
+            });
+        }
+
+        // only the landscape needs the records, the heightmap is drawn from heights
+        let mut land_records = HashMap::default();
+        if self.settings.background == EBackground::Landscape {
+            for cy in dimensions.min_y..=dimensions.max_y {
+                for cx in dimensions.min_x..=dimensions.max_x {
+                    if let Some(landscape) = world.land_records.get(&(cx, cy)) {
+                        land_records.insert((cx, cy), landscape.clone());
+                    }
+                }
+            }
+        }
+
+        let padded = get_padded_dimensions(&world.dimensions, &dimensions);
+        let job = TileJob {
+            generation: self.generation.load(Ordering::Relaxed),
+            key,
+            lod,
+            heights: crop_heights(&world.heights, &world.dimensions, &padded),
+            padded,
+            dimensions,
+            land_records,
+            settings: self.settings.clone(),
+        };
+        if sender.send(TileMessage::Render(Box::new(job))).is_ok() {
+            self.pending.insert((key, lod));
+        }
+    }
+
+    fn spawn(&self, ctx: &egui::Context) -> (Sender<TileMessage>, Receiver<TileResult>) {
+        let (job_sender, job_receiver) = mpsc::channel::<TileMessage>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let generation = self.generation.clone();
+        let current_lod = self.lod.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let mut vfs = Vfs::default();
+            let mut ltex_records = HashMap::default();
+            // texture maps by texture size
+            let mut texture_maps: HashMap<usize, HashMap<String, ImageBuffer>> = HashMap::default();
+
+            // ends when the cache is dropped
+            while let Ok(message) = job_receiver.recv() {
+                let job = match message {
+                    TileMessage::Reset {
+                        vfs: new_vfs,
+                        ltex_records: new_ltex_records,
+                    } => {
+                        vfs = new_vfs;
+                        ltex_records = new_ltex_records;
+                        texture_maps.clear();
+                        continue;
+                    }
+                    TileMessage::Render(job) => job,
+                };
+
+                // skip jobs that are outdated by now
+                let image = if job.generation != generation.load(Ordering::Relaxed)
+                    || job.lod != current_lod.load(Ordering::Relaxed)
+                {
+                    None
+                } else {
+                    debug!(
+                        "Rendering tile {:?} at {} pixels per cell",
+                        job.key, job.lod
+                    );
+                    render_tile(&job, &vfs, &ltex_records, &mut texture_maps)
+                };
+
+                let result = TileResult {
+                    generation: job.generation,
+                    key: job.key,
+                    lod: job.lod,
+                    image,
+                };
+                if result_sender.send(result).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+
+        (job_sender, result_receiver)
+    }
+}
+
+fn render_tile(
+    job: &TileJob,
+    vfs: &Vfs,
+    ltex_records: &HashMap<u32, LandscapeTexture>,
+    texture_maps: &mut HashMap<usize, HashMap<String, ImageBuffer>>,
+) -> Option<ColorImage> {
+    let tile_heights = || crop_heights(&job.heights, &job.padded, &job.dimensions);
+    match job.settings.background {
+        EBackground::Landscape => {
+            let texture_size = job.lod / GRID_SIZE;
+            let texture_map = texture_maps.entry(texture_size).or_default();
+            load_texture_map(
+                vfs,
+                &job.dimensions,
+                &job.land_records,
+                ltex_records,
+                texture_size,
+                texture_map,
+            );
+
+            let settings = LandscapeSettings {
+                texture_size,
+                ..job.settings.landscape_settings.clone()
+            };
+            let mut image = compute_landscape_image(
+                &settings,
+                &job.dimensions,
+                &job.land_records,
+                ltex_records,
+                &tile_heights(),
+                texture_map,
+            );
+            blend_hillshade(
+                &mut image,
+                &job.dimensions,
+                &job.heights,
+                &job.padded,
+                &job.settings.terrain_settings,
+            );
+            Some(image)
+        }
+        EBackground::HeightMap => {
+            let image = generate_heightmap(
+                &tile_heights(),
+                &job.dimensions,
+                &job.settings.heightmap_settings,
+            );
+            Some(downsample(&image, job.dimensions.pixel_size_tuple(job.lod)))
+        }
+        EBackground::Hillshade | EBackground::Slope | EBackground::Aspect => {
+            let settings = &job.settings.terrain_settings;
+            let padded = match job.settings.background {
+                EBackground::Hillshade => generate_hillshade(&job.heights, &job.padded, settings),
+                EBackground::Slope => generate_slope_map(&job.heights, &job.padded, settings),
+                _ => generate_aspect_map(&job.heights, &job.padded),
+            };
+            let image = crop_image(&padded, &job.padded, &job.dimensions);
+            Some(downsample(&image, job.dimensions.pixel_size_tuple(job.lod)))
+        }
+        _ => None,
+    }
+}
+
+/// Cells of a tile, clipped to the map
+fn get_tile_dimensions(dimensions: &Dimensions, key: TileKey) -> Dimensions {
+    Dimensions {
+        min_x: (key.0 * TILE_CELLS).max(dimensions.min_x),
+        min_y: (key.1 * TILE_CELLS).max(dimensions.min_y),
+        max_x: (key.0 * TILE_CELLS + TILE_CELLS - 1).min(dimensions.max_x),
+        max_y: (key.1 * TILE_CELLS + TILE_CELLS - 1).min(dimensions.max_y),
+        min_z: dimensions.min_z,
+        max_z: dimensions.max_z,
+    }
+}
+
+fn get_tile_rect(dimensions: &Dimensions, tile: &Dimensions, to_screen: RectTransform) -> Rect {
+    let p00 = dimensions.tranform_to_canvas((tile.min_x, tile.max_y));
+    let p11 = pos2(p00.x + tile.width() as f32, p00.y + tile.height() as f32);
+    Rect::from_two_pos(to_screen * p00, to_screen * p11)
+}
+
+/// The cells of a tile and the cells around it, clipped to the map
+fn get_padded_dimensions(dimensions: &Dimensions, tile: &Dimensions) -> Dimensions {
+    Dimensions {
+        min_x: (tile.min_x - 1).max(dimensions.min_x),
+        min_y: (tile.min_y - 1).max(dimensions.min_y),
+        max_x: (tile.max_x + 1).min(dimensions.max_x),
+        max_y: (tile.max_y + 1).min(dimensions.max_y),
+        ..tile.clone()
+    }
+}
+
+/// Copy the pixels of a tile out of an image of more cells, VERTEX_CNT pixels per cell
+fn crop_image(image: &ColorImage, dimensions: &Dimensions, tile: &Dimensions) -> ColorImage {
+    let x0 = dimensions.tranform_to_canvas_x(tile.min_x) * VERTEX_CNT;
+    let y0 = dimensions.tranform_to_canvas_y(tile.max_y) * VERTEX_CNT;
+    let [width, height] = tile.pixel_size_tuple(VERTEX_CNT);
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in y0..y0 + height {
+        let start = y * image.size[0] + x0;
+        pixels.extend_from_slice(&image.pixels[start..start + width]);
+    }
+    ColorImage {
+        size: [width, height],
+        pixels,
+    }
+}
+
+/// Copy the heights of a tile out of the heights of a larger area
+fn crop_heights(heights: &[f32], dimensions: &Dimensions, tile: &Dimensions) -> Vec<f32> {
+    let stride = dimensions.stride(VERTEX_CNT);
+    let x0 = dimensions.tranform_to_canvas_x(tile.min_x) * VERTEX_CNT;
+    let y0 = dimensions.tranform_to_canvas_y(tile.max_y) * VERTEX_CNT;
+    let width = tile.pixel_width(VERTEX_CNT);
+
+    let mut tile_heights = Vec::with_capacity(tile.pixel_size(VERTEX_CNT));
+    for y in y0..y0 + tile.pixel_height(VERTEX_CNT) {
+        let start = y * stride + x0;
+        match heights.get(start..start + width) {
+            Some(row) => tile_heights.extend_from_slice(row),
+            None => tile_heights.resize(tile_heights.len() + width, dimensions.min_z - 1.0),
+        }
+    }
+    tile_heights
+}
+
+/// Nearest neighbour resize to a smaller size
+fn downsample(image: &ColorImage, size: [usize; 2]) -> ColorImage {
+    if image.size == size {
+        return image.clone();
+    }
+
+    let mut pixels = Vec::with_capacity(size[0] * size[1]);
+    for y in 0..size[1] {
+        let sy = y * image.size[1] / size[1];
+        for x in 0..size[0] {
+            let sx = x * image.size[0] / size[0];
+            pixels.push(image.pixels[sy * image.size[0] + sx]);
+        }
+    }
+    ColorImage { size, pixels }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_hillshade_matches_the_whole_map() {
+        // two tiles side by side, a valley running north to south along the tile edge
+        let dimensions = Dimensions {
+            min_x: 0,
+            max_x: 2 * TILE_CELLS - 1,
+            min_y: 0,
+            max_y: 0,
+            min_z: 0.0,
+            max_z: 4096.0,
+        };
+        let stride = dimensions.stride(VERTEX_CNT);
+        let heights = (0..dimensions.pixel_size(VERTEX_CNT))
+            .map(|i| {
+                let x = i % stride;
+                let vertex = x / VERTEX_CNT * (VERTEX_CNT - 1) + x % VERTEX_CNT;
+                (vertex as f32 - (TILE_CELLS as usize * (VERTEX_CNT - 1)) as f32).abs() * 32.0
+            })
+            .collect::<Vec<_>>();
+        let settings = TerrainSettings::default();
+        let map = generate_hillshade(&heights, &dimensions, &settings);
+
+        for key in [(0, 0), (1, 0)] {
+            let tile = get_tile_dimensions(&dimensions, key);
+            let padded = get_padded_dimensions(&dimensions, &tile);
+            let tile_heights = crop_heights(&heights, &dimensions, &padded);
+            let image = generate_hillshade(&tile_heights, &padded, &settings);
+
+            assert_eq!(
+                crop_image(&image, &padded, &tile),
+                crop_image(&map, &dimensions, &tile)
+            );
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use tes3::esp::{Cell, Creature, Npc, Plugin, TravelDestination};
+
+use crate::doors::InteriorHomes;
+use crate::interior::get_placed_references;
+use crate::*;
+
+/// Class of travel routes offered by creatures, which have no class
+pub const CREATURE_CLASS: &str = "Creature";
+
+/// Default of the fTravelMult game setting, game units per gold
+const TRAVEL_MULT: f32 = 4000.0;
+/// Default of the fMagesGuildTravel game setting, the price of travel from interiors
+const MAGES_GUILD_TRAVEL: u32 = 10;
+/// Default of the fTravelTimeMult game setting, game units per hour of travel
+const TRAVEL_TIME_MULT: f32 = 16000.0;
+
+/// Travel destinations of an NPC or creature record
+#[derive(Debug, Clone, Default)]
+pub struct TravelService {
+    pub id: String,
+    pub name: String,
+    pub class: String,
+    pub destinations: Vec<TravelDestination>,
+    /// the last plugin that defines the record
+    pub plugin: String,
+}
+
+/// A destination offered by a placed NPC or creature
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TravelRoute {
+    pub npc: String,
+    pub name: String,
+    pub class: String,
+    pub plugin: String,
+    /// position of the actor, or of the exterior door into its interior
+    pub from: [f32; 3],
+    /// destination, or the exterior door into the destination interior
+    pub to: [f32; 3],
+    /// name of the destination interior, empty for exterior destinations
+    pub interior: String,
+    /// base price in gold before disposition and followers, if it can be told from the records
+    pub cost: Option<u32>,
+    /// in-game hours the travel takes, travel from interiors is instant
+    pub hours: f32,
+}
+
+impl TravelRoute {
+    pub fn from_cell(&self) -> CellKey {
+        get_cell_at_translation(self.from)
+    }
+
+    pub fn to_cell(&self) -> CellKey {
+        get_cell_at_translation(self.to)
+    }
+
+    /// One line for tooltips
+    pub fn describe(&self, cell_records: &HashMap<CellKey, Cell>) -> String {
+        let destination = if self.interior.is_empty() {
+            get_cell_name(cell_records, self.to_cell())
+        } else {
+            self.interior.clone()
+        };
+        let cost = self
+            .cost
+            .map(|c| format!("{} gold", c))
+            .unwrap_or("unknown cost".to_owned());
+        let npc = if self.name.is_empty() {
+            self.npc.clone()
+        } else {
+            format!("{} ({})", self.name, self.npc)
+        };
+        format!(
+            "{} [{}] to {}, {}, {}",
+            npc, self.class, destination, cost, self.plugin
+        )
+    }
+}
+
+/// Add the travel services of all NPCs and creatures of a plugin, replacing earlier records
+pub fn add_travel_services(
+    services: &mut HashMap<String, TravelService>,
+    plugin: &Plugin,
+    plugin_name: &str,
+) {
+    let npcs = plugin.objects_of_type::<Npc>().map(|npc| {
+        (
+            &npc.id,
+            &npc.name,
+            npc.class.clone(),
+            &npc.travel_destinations,
+        )
+    });
+    let creatures = plugin.objects_of_type::<Creature>().map(|creature| {
+        (
+            &creature.id,
+            &creature.name,
+            CREATURE_CLASS.to_owned(),
+            &creature.travel_destinations,
+        )
+    });
+
+    for (id, name, class, destinations) in npcs.chain(creatures) {
+        let key = id.to_lowercase();
+        if destinations.is_empty() {
+            // a later plugin may remove the service
+            services.remove(&key);
+            continue;
+        }
+        services.insert(
+            key,
+            TravelService {
+                id: id.clone(),
+                name: name.clone(),
+                class,
+                destinations: destinations.clone(),
+                plugin: plugin_name.to_owned(),
+            },
+        );
+    }
+}
+
+/// All destinations of every placed travel NPC or creature.
+/// Actors and destinations in interiors are moved to the exterior door of the interior,
+/// interiors without one are skipped
+pub fn find_travel_routes(
+    services: &HashMap<String, TravelService>,
+    cell_records: &HashMap<CellKey, Cell>,
+    interior_records: &HashMap<String, Cell>,
+    homes: &InteriorHomes,
+) -> Vec<TravelRoute> {
+    let home = |interior: &str| homes.get(&interior.to_lowercase()).and_then(|h| h.first());
+
+    // placed actors with their exterior position, and whether they stand in an interior
+    let mut actors: Vec<(&TravelService, [f32; 3], [f32; 3], bool)> = vec![];
+    let mut keys = cell_records.keys().copied().collect::<Vec<_>>();
+    keys.sort();
+    for key in keys {
+        for reference in get_placed_references(&cell_records[&key]) {
+            if let Some(service) = services.get(&reference.id.to_lowercase()) {
+                actors.push((service, reference.translation, reference.translation, false));
+            }
+        }
+    }
+    let mut ids = interior_records.keys().collect::<Vec<_>>();
+    ids.sort();
+    for id in ids {
+        let Some(from) = home(id) else {
+            continue;
+        };
+        for reference in get_placed_references(&interior_records[id]) {
+            if let Some(service) = services.get(&reference.id.to_lowercase()) {
+                actors.push((service, *from, reference.translation, true));
+            }
+        }
+    }
+
+    let mut routes = vec![];
+    for (service, from, position, in_interior) in actors {
+        for destination in &service.destinations {
+            let (to, interior) = if destination.cell.is_empty() {
+                (destination.translation, String::new())
+            } else {
+                let Some(to) = home(&destination.cell) else {
+                    continue;
+                };
+                (*to, destination.cell.clone())
+            };
+
+            // the game prices travel from interiors with a fixed fee, otherwise by distance.
+            // Interior destinations are priced by their interior coordinates, which mean nothing here
+            let cost = if in_interior {
+                Some(MAGES_GUILD_TRAVEL)
+            } else if interior.is_empty() {
+                Some((get_distance(position, destination.translation) / TRAVEL_MULT) as u32)
+            } else {
+                None
+            };
+            let hours = if in_interior {
+                0.0
+            } else {
+                (get_distance(from, to) / TRAVEL_TIME_MULT).floor()
+            };
+
+            routes.push(TravelRoute {
+                npc: service.id.clone(),
+                name: service.name.clone(),
+                class: service.class.clone(),
+                plugin: service.plugin.clone(),
+                from,
+                to,
+                interior,
+                cost,
+                hours,
+            });
+        }
+    }
+    routes
+}
@@ -0,0 +1,268 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+
+use crate::load_order::LoadOrder;
+
+/// A file inside a bsa archive
+#[derive(Debug, Clone)]
+struct ArchiveEntry {
+    /// index into Vfs::archives
+    archive: usize,
+    offset: u64,
+    size: u32,
+}
+
+/// Virtual file system: loose files in the data directories layered over bsa archives
+#[derive(Debug, Clone, Default)]
+pub struct Vfs {
+    /// archive paths in load order
+    archives: Vec<PathBuf>,
+    /// normalized file path -> loose file, later data directories override earlier ones
+    loose_files: HashMap<String, PathBuf>,
+    /// normalized file path -> archive entry, later archives override earlier ones
+    archive_entries: HashMap<String, ArchiveEntry>,
+}
+
+impl Vfs {
+    /// Create a vfs from the data directories and archives of a load order
+    pub fn from_load_order(load_order: &LoadOrder) -> Self {
+        Self::new(&load_order.data_dirs, &load_order.archives)
+    }
+
+    /// Archives are looked up by name in the data directories
+    pub fn new(data_dirs: &[PathBuf], archives: &[String]) -> Self {
+        let mut vfs = Vfs::default();
+
+        for dir in data_dirs {
+            index_loose_files(dir, dir, &mut vfs.loose_files);
+        }
+        info!("Indexed {} loose files", vfs.loose_files.len());
+
+        for name in archives {
+            let Some(path) = vfs.find_loose_file(name) else {
+                warn!("Archive {} not found in data directories", name);
+                continue;
+            };
+
+            match read_bsa_index(&path) {
+                Ok(entries) => {
+                    info!("Loaded archive {} with {} files", path.display(), entries.len());
+                    let archive = vfs.archives.len();
+                    vfs.archives.push(path);
+                    for (name, offset, size) in entries {
+                        vfs.archive_entries.insert(
+                            name,
+                            ArchiveEntry {
+                                archive,
+                                offset,
+                                size,
+                            },
+                        );
+                    }
+                }
+                Err(e) => warn!("Failed to read archive {}: {}", path.display(), e),
+            }
+        }
+
+        vfs
+    }
+
+    /// Check if a file exists as loose file or in an archive
+    pub fn exists(&self, path: &str) -> bool {
+        self.find_loose_file(path).is_some()
+            || self.archive_entries.contains_key(&normalize_path(path))
+    }
+
+    /// Read a file, loose files take priority over archives
+    pub fn read(&self, path: &str) -> std::io::Result<Vec<u8>> {
+        if let Some(loose) = self.find_loose_file(path) {
+            return fs::read(loose);
+        }
+
+        let Some(entry) = self.archive_entries.get(&normalize_path(path)) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("File not found: {}", path),
+            ));
+        };
+
+        let mut file = File::open(&self.archives[entry.archive])?;
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut buffer = vec![0; entry.size as usize];
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Find a loose file in the data directories, case insensitive
+    fn find_loose_file(&self, path: &str) -> Option<PathBuf> {
+        self.loose_files.get(&normalize_path(path)).cloned()
+    }
+}
+
+/// Add all files below dir to the index, keyed by their normalized path relative to root
+fn index_loose_files(root: &Path, dir: &Path, files: &mut HashMap<String, PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.is_dir() {
+            index_loose_files(root, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.insert(normalize_path(&relative.to_string_lossy()), path);
+        }
+    }
+}
+
+/// Archive file names are lowercase with backslashes
+fn normalize_path(path: &str) -> String {
+    path.replace('/', "\\").to_lowercase()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> std::io::Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "Truncated bsa header")
+        })
+}
+
+/// Read the file table of a Morrowind bsa.
+/// Returns the normalized file name, absolute offset and size of each file
+fn read_bsa_index(path: &Path) -> std::io::Result<Vec<(String, u64, u32)>> {
+    let mut file = File::open(path)?;
+
+    // header: version, hash table offset, file count
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header)?;
+    let version = read_u32(&header, 0)?;
+    if version != 0x100 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Unsupported bsa version: {:#x}", version),
+        ));
+    }
+    let hash_offset = read_u32(&header, 4)? as usize;
+    let file_count = read_u32(&header, 8)? as usize;
+
+    // file records, name offsets and names are all before the hash table
+    let mut table = vec![0u8; hash_offset];
+    file.read_exact(&mut table)?;
+
+    let names_start = file_count * 12;
+    let data_start = (12 + hash_offset + file_count * 8) as u64;
+
+    let mut entries = Vec::with_capacity(file_count);
+    for i in 0..file_count {
+        let size = read_u32(&table, i * 8)?;
+        let offset = read_u32(&table, i * 8 + 4)?;
+        let name_offset = read_u32(&table, file_count * 8 + i * 4)? as usize;
+
+        let name_bytes = table.get(names_start + name_offset..).unwrap_or_default();
+        let end = name_bytes
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(name_bytes.len());
+        let name = String::from_utf8_lossy(&name_bytes[..end]);
+
+        entries.push((normalize_path(&name), data_start + offset as u64, size));
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tes3map-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A Morrowind bsa with the given files, the hash table is left empty
+    fn write_bsa(path: &Path, files: &[(&str, &[u8])]) {
+        let count = files.len();
+        let mut records = vec![];
+        let mut name_offsets = vec![];
+        let mut names = vec![];
+        let mut data: Vec<u8> = vec![];
+        for (name, contents) in files {
+            records.extend((contents.len() as u32).to_le_bytes());
+            records.extend((data.len() as u32).to_le_bytes());
+            name_offsets.extend((names.len() as u32).to_le_bytes());
+            names.extend(name.as_bytes());
+            names.push(0);
+            data.extend(*contents);
+        }
+        let hash_offset = records.len() + name_offsets.len() + names.len();
+
+        let mut bytes = vec![];
+        bytes.extend(0x100_u32.to_le_bytes());
+        bytes.extend((hash_offset as u32).to_le_bytes());
+        bytes.extend((count as u32).to_le_bytes());
+        bytes.extend(records);
+        bytes.extend(name_offsets);
+        bytes.extend(names);
+        bytes.extend(vec![0; count * 8]);
+        bytes.extend(data);
+        fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn bsa_index() {
+        let dir = temp_dir("bsa-index");
+        let path = dir.join("Test.bsa");
+        write_bsa(
+            &path,
+            &[("textures\\a.dds", b"aaa"), ("Textures/Sub/B.DDS", b"bb")],
+        );
+
+        let entries = read_bsa_index(&path).unwrap();
+        let data_start = 12 + 2 * 12 + 15 + 19 + 2 * 8;
+        assert_eq!(
+            entries,
+            [
+                ("textures\\a.dds".to_owned(), data_start, 3),
+                ("textures\\sub\\b.dds".to_owned(), data_start + 3, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_bsa_version() {
+        let dir = temp_dir("bsa-version");
+        let path = dir.join("Skyrim.bsa");
+        fs::write(&path, [0x42, 0x53, 0x41, 0, 0x68, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+
+        let error = read_bsa_index(&path).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn loose_files_override_archives() {
+        let dir = temp_dir("vfs");
+        write_bsa(
+            &dir.join("Test.bsa"),
+            &[
+                ("textures\\a.dds", b"archived a"),
+                ("textures\\b.dds", b"archived b"),
+            ],
+        );
+        fs::create_dir_all(dir.join("Textures")).unwrap();
+        fs::write(dir.join("Textures").join("A.dds"), b"loose a").unwrap();
+
+        let vfs = Vfs::new(&[dir], &["test.bsa".to_owned(), "Missing.bsa".to_owned()]);
+        assert_eq!(vfs.read("textures/a.dds").unwrap(), b"loose a");
+        assert_eq!(vfs.read("Textures\\B.dds").unwrap(), b"archived b");
+        assert!(vfs.exists("textures\\b.dds"));
+        assert!(!vfs.exists("textures\\c.dds"));
+    }
+}
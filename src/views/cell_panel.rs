@@ -36,12 +36,12 @@ impl TemplateApp {
         egui::ScrollArea::vertical()
             .auto_shrink([false, true])
             .show(ui, |ui| {
-                let mut ids = self.cell_records.keys().collect::<Vec<_>>();
+                let mut ids = self.world.cell_records.keys().collect::<Vec<_>>();
                 ids.sort();
 
                 for key in ids {
                     // upper and lowercase search
-                    let cell_name = self.cell_records.get(key).unwrap().editor_id();
+                    let cell_name = self.world.cell_records.get(key).unwrap().editor_id();
                     let label_text = format!("{:?} - {}", key, cell_name);
                     if !self.runtime_data.cell_filter.is_empty()
                         && !label_text
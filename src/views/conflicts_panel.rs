@@ -0,0 +1,132 @@
+use egui::{Color32, RichText};
+
+use crate::conflicts::{get_winners, has_overridden_landscape, PluginEdit};
+use crate::TemplateApp;
+
+impl TemplateApp {
+    pub fn conflicts_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Conflicts");
+
+        // the plugins touching the selected cell
+        match self.runtime_data.selected_ids.last().copied() {
+            Some(key) => {
+                let name = self
+                    .world
+                    .cell_records
+                    .get(&key)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_default();
+                ui.label(RichText::new(format!("{:?} {}", key, name)).strong());
+
+                match self.world.cell_edits.get(&key) {
+                    Some(edits) => self.plugin_edits(ui, edits),
+                    None => {
+                        ui.label("No plugin touches this cell");
+                    }
+                }
+            }
+            None => {
+                ui.label("Select a cell on the map");
+            }
+        }
+
+        ui.separator();
+
+        // all cells touched by more than one plugin
+        ui.checkbox(
+            &mut self.runtime_data.conflicts_landscape_only,
+            "Only overridden landscape",
+        )
+        .on_hover_text("Cells where a plugin's landscape edits are replaced by a later plugin");
+
+        let mut keys = self
+            .world
+            .cell_edits
+            .iter()
+            .filter(|(_, edits)| edits.len() > 1)
+            .filter(|(_, edits)| {
+                !self.runtime_data.conflicts_landscape_only || has_overridden_landscape(edits)
+            })
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        keys.sort();
+        ui.label(format!("{} cells", keys.len()));
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for key in keys {
+                    let name = self
+                        .world
+                        .cell_records
+                        .get(&key)
+                        .map(|c| c.name.clone())
+                        .unwrap_or_default();
+                    let selected = self.runtime_data.selected_ids.last() == Some(&key);
+                    if ui
+                        .selectable_label(selected, format!("{:?} - {}", key, name))
+                        .clicked()
+                    {
+                        self.runtime_data.selected_ids = vec![key];
+                    }
+                }
+            });
+    }
+
+    /// One group per plugin in load order with the subrecords it changes.
+    /// Green subrecords end up in game, red ones are replaced by a later plugin
+    fn plugin_edits(&self, ui: &mut egui::Ui, edits: &[PluginEdit]) {
+        let winners = get_winners(edits);
+        let cell_winner = edits.iter().rev().find(|e| e.cell).map(|e| e.plugin);
+        let land_winner = edits.iter().rev().find(|e| e.land).map(|e| e.plugin);
+
+        for edit in edits {
+            let name = self
+                .world
+                .plugins
+                .iter()
+                .find(|p| p.hash == edit.plugin)
+                .map(|p| p.get_name())
+                .unwrap_or_default();
+
+            ui.group(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new(name).strong());
+                    if edit.cell {
+                        let text = RichText::new("CELL");
+                        ui.label(if cell_winner == Some(edit.plugin) {
+                            text.color(Color32::GREEN)
+                        } else {
+                            text
+                        });
+                    }
+                    if edit.land {
+                        let text = RichText::new("LAND");
+                        ui.label(if land_winner == Some(edit.plugin) {
+                            text.color(Color32::GREEN)
+                        } else {
+                            text
+                        });
+                    }
+                });
+
+                let changes = edit.changes();
+                if changes.is_empty() {
+                    ui.label("No changes");
+                    return;
+                }
+                ui.horizontal_wrapped(|ui| {
+                    for subrecord in changes {
+                        let color = if winners.get(&subrecord) == Some(&edit.plugin) {
+                            Color32::GREEN
+                        } else {
+                            Color32::RED
+                        };
+                        ui.label(RichText::new(subrecord.label()).color(color))
+                            .on_hover_text(edit.describe(subrecord));
+                    }
+                });
+            });
+        }
+    }
+}
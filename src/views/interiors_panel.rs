@@ -0,0 +1,117 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{get_cell_name, CellKey, TemplateApp};
+
+impl TemplateApp {
+    pub fn interiors_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Interiors");
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.interior.is_some(), egui::Button::new("Exterior"))
+                .on_hover_text("Back to the exterior map")
+                .clicked()
+            {
+                self.interior = None;
+                self.reset_pan();
+                self.reset_zoom();
+            }
+        });
+
+        ui.separator();
+
+        // search bar
+        ui.horizontal(|ui| {
+            ui.label("Filter: ");
+            ui.text_edit_singleline(&mut self.runtime_data.interior_filter);
+            // clear filter button
+            if ui.button("x").clicked() {
+                self.runtime_data.interior_filter.clear();
+            }
+        });
+
+        ui.checkbox(
+            &mut self.runtime_data.interiors_by_entrance,
+            "Group by entrance cell",
+        );
+
+        let filter = self.runtime_data.interior_filter.to_lowercase();
+        let mut ids = self
+            .world
+            .interior_records
+            .keys()
+            .filter(|id| filter.is_empty() || id.contains(&filter))
+            .cloned()
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        if self.runtime_data.interiors_by_entrance {
+            self.interiors_by_entrance(ui, ids);
+            return;
+        }
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, true])
+            .show_rows(
+                ui,
+                ui.text_style_height(&egui::TextStyle::Body),
+                ids.len(),
+                |ui, range| {
+                    for id in &ids[range] {
+                        self.interior_label(ui, id);
+                    }
+                },
+            );
+    }
+
+    /// Interiors below the exterior cells with a door into them
+    fn interiors_by_entrance(&mut self, ui: &mut egui::Ui, ids: Vec<String>) {
+        let mut groups: BTreeMap<CellKey, BTreeSet<String>> = BTreeMap::new();
+        let ids = ids.into_iter().collect::<BTreeSet<_>>();
+        let mut other = ids.clone();
+        for entrance in &self.world.door_entrances {
+            if ids.contains(&entrance.interior) {
+                other.remove(&entrance.interior);
+                groups
+                    .entry(entrance.cell)
+                    .or_default()
+                    .insert(entrance.interior.clone());
+            }
+        }
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for (key, interiors) in groups {
+                    let name = get_cell_name(&self.world.cell_records, key);
+                    egui::CollapsingHeader::new(format!("{} - {}", name, interiors.len()))
+                        .id_source(key)
+                        .show(ui, |ui| {
+                            for id in &interiors {
+                                self.interior_label(ui, id);
+                            }
+                        });
+                }
+
+                // only reachable through other interiors or not at all
+                if !other.is_empty() {
+                    egui::CollapsingHeader::new(format!("No exterior door ({})", other.len()))
+                        .show(ui, |ui| {
+                            for id in &other {
+                                self.interior_label(ui, id);
+                            }
+                        });
+                }
+            });
+    }
+
+    fn interior_label(&mut self, ui: &mut egui::Ui, id: &String) {
+        let name = &self.world.interior_records[id].name;
+        let selected = self.interior.as_ref() == Some(id);
+        if ui.selectable_label(selected, name).clicked() {
+            self.interior = Some(id.clone());
+            self.reset_pan();
+            self.reset_zoom();
+        }
+    }
+}
@@ -4,37 +4,75 @@ use egui::Sense;
 use log::info;
 
 use crate::app::TooltipInfo;
-use crate::overlay::cities::get_cities_shapes;
-use crate::overlay::interventions::get_intervention_shapes;
-use crate::overlay::conflicts::get_conflict_shapes;
-use crate::overlay::grid::get_grid_shapes;
-use crate::overlay::regions::get_region_shapes;
-use crate::overlay::travel::get_travel_shapes;
+use crate::interior::{get_interior_bounds, get_object_type, get_reference_at};
+use crate::overlay::references::get_reference_at as get_exterior_reference_at;
+use crate::overlay::regions::get_outline_shapes;
+use crate::overlay::route::get_route_shapes;
+use crate::region::{get_region, get_region_cells, get_weather_chances};
 use crate::*;
 
 impl TemplateApp {
     fn cellkey_from_screen(&mut self, from_screen: RectTransform, pointer_pos: Pos2) -> CellKey {
         let transformed_position = from_screen * pointer_pos;
         // get cell grid
-        self.dimensions
+        self.world.dimensions
             .tranform_to_cell(Pos2::new(transformed_position.x, transformed_position.y))
     }
 
+    fn interior_tooltip(&self, ui: &egui::Ui, interior: &str, pos: Pos2) {
+        if !self.ui_data.show_tooltips || !ui.ui_contains_pointer() {
+            return;
+        }
+        let Some(cell) = self.world.interior_records.get(interior) else {
+            return;
+        };
+        let Some(reference) = get_reference_at(cell, &self.world.object_types, pos) else {
+            return;
+        };
+
+        egui::show_tooltip(
+            ui.ctx(),
+            ui.layer_id(),
+            egui::Id::new("hover_tooltip"),
+            |ui| {
+                let object_type = get_object_type(&self.world.object_types, &reference.id);
+                ui.label(format!("{} ({})", reference.id, object_type.label()));
+                ui.label(format!(
+                    "({:.0}, {:.0}, {:.0})",
+                    reference.translation[0], reference.translation[1], reference.translation[2]
+                ));
+                if let Some(destination) = &reference.door_destination_cell {
+                    ui.label(format!("Door to {}", destination));
+                } else if reference.door_destination_coords.is_some() {
+                    ui.label("Door to exterior");
+                }
+            },
+        );
+    }
+
     pub fn map_panel(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         // The central panel the region left after adding TopPanel's and SidePanel's
-        ui.heading(format!(
-            "Map (y: [{},{}]; x: [{},{}]; z: [{},{}])",
-            self.dimensions.min_y,
-            self.dimensions.max_y,
-            self.dimensions.min_x,
-            self.dimensions.max_x,
-            self.dimensions.min_z,
-            self.dimensions.max_z
-        ));
+        if let Some(cell) = self
+            .interior
+            .as_ref()
+            .and_then(|id| self.world.interior_records.get(id))
+        {
+            ui.heading(format!("Interior: {}", cell.name));
+        } else {
+            ui.heading(format!(
+                "Map (y: [{},{}]; x: [{},{}]; z: [{},{}])",
+                self.world.dimensions.min_y,
+                self.world.dimensions.max_y,
+                self.world.dimensions.min_x,
+                self.world.dimensions.max_x,
+                self.world.dimensions.min_z,
+                self.world.dimensions.max_z
+            ));
+        }
 
         ui.separator();
 
-        if self.heights.is_empty() {
+        if self.world.heights.is_empty() && self.interior.is_none() {
             // settings
             egui::Frame::popup(ui.style())
                 .stroke(Stroke::NONE)
@@ -71,10 +109,21 @@ impl TemplateApp {
         }
 
         // transforms
-        let real_width = self.dimensions.width() as f32;
-        let real_height = self.dimensions.height() as f32;
-        let from: Rect = Rect::from_min_max(pos2(0.0, 0.0), pos2(real_width, real_height));
-        let r = real_height / real_width;
+        let from: Rect = match &self.interior {
+            // plan space of the interior
+            Some(interior) => self
+                .world
+                .interior_records
+                .get(interior)
+                .map(get_interior_bounds)
+                .unwrap_or(Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0))),
+            None => {
+                let real_width = self.world.dimensions.width() as f32;
+                let real_height = self.world.dimensions.height() as f32;
+                Rect::from_min_max(pos2(0.0, 0.0), pos2(real_width, real_height))
+            }
+        };
+        let r = from.height() / from.width();
 
         let min = self.zoom_data.drag_offset;
         let max = Pos2::new(response.rect.max.x, response.rect.max.x * r) * self.zoom_data.zoom
@@ -85,91 +134,84 @@ impl TemplateApp {
         let from_screen = to_screen.inverse();
 
         // paint maps
+        if let Some(interior) = &self.interior {
+            for (_, shapes) in self.world.get_interior_layers(interior, to_screen) {
+                painter.extend(shapes);
+            }
+        } else {
+            let uv = Rect::from_min_max(pos2(0.0, 0.0), Pos2::new(1.0, 1.0));
 
-        let uv = Rect::from_min_max(pos2(0.0, 0.0), Pos2::new(1.0, 1.0));
+            // Background
+            if let Some(handle) = &self.background_handle {
+                painter.image(handle.into(), canvas, uv, Color32::WHITE);
+            }
+            self.tiles
+                .paint(ctx, &painter, &self.world, &self.vfs, to_screen);
 
-        // Background
-        if let Some(handle) = &self.background_handle {
-            painter.image(handle.into(), canvas, uv, Color32::WHITE);
-        }
+            // Overlays
+            if self.ui_data.overlay_paths {
+                if let Some(handle) = &self.paths_handle {
+                    painter.image(handle.into(), canvas, uv, Color32::WHITE);
+                }
+            }
+            for overlay in self.ui_data.get_overlays() {
+                let shapes = ctx.fonts(|fonts| {
+                    self.world.get_visible_overlay_shapes(
+                        overlay,
+                        to_screen,
+                        painter.clip_rect(),
+                        &self.ui_data,
+                        &self.intervention_engine,
+                        fonts,
+                    )
+                });
+                painter.extend(shapes);
+            }
 
-        // Overlays
-        if self.ui_data.overlay_paths {
-            if let Some(handle) = &self.paths_handle {
-                painter.image(handle.into(), canvas, uv, Color32::WHITE);
+            // extent of the selected region
+            if let Some(region) = &self.runtime_data.selected_region {
+                let cells = get_region_cells(&self.world.cell_records, region);
+                painter.extend(get_outline_shapes(
+                    to_screen,
+                    &self.world.dimensions,
+                    &cells,
+                    Stroke::new(3.0, Color32::YELLOW),
+                ));
             }
-        }
-        if self.ui_data.overlay_region {
-            let shapes = get_region_shapes(
-                to_screen,
-                &self.dimensions,
-                &self.regn_records,
-                &self.cell_records,
-            );
-            painter.extend(shapes);
-        }
-        if self.ui_data.overlay_grid {
-            let shapes = get_grid_shapes(to_screen, &self.dimensions);
-            painter.extend(shapes);
-        }
-        if self.ui_data.overlay_alm_interventions {
-            let shapes = get_intervention_shapes(
-                to_screen, &self.dimensions, 
-                &self.almsivi_interventions, &self.cell_records, 
-                "almsivi", &self.intervention_engine
-            );
-            painter.extend(shapes);
-        }
-        if self.ui_data.overlay_div_interventions {
-            let shapes = get_intervention_shapes(
-                to_screen, &self.dimensions, 
-                &self.divine_interventions, &self.cell_records, 
-                "divine", &self.intervention_engine
-            );
-            painter.extend(shapes);
-        }
-        if self.ui_data.overlay_kyn_interventions {
-            let shapes = get_intervention_shapes(
-                to_screen, &self.dimensions, 
-                &self.kyne_interventions, &self.cell_records, 
-                "kyne", &self.intervention_engine
-            );
-            painter.extend(shapes);
-        }
-        if self.ui_data.overlay_cities {
-            let shapes = get_cities_shapes(to_screen, &self.dimensions, &self.cell_records);
-            painter.extend(shapes);
-        }
-        if self.ui_data.overlay_travel {
-            let shapes = get_travel_shapes(to_screen, &self.dimensions, &self.travel_edges);
-            painter.extend(shapes);
-        }
-        if self.ui_data.overlay_conflicts {
-            let shapes = get_conflict_shapes(to_screen, &self.dimensions, &self.cell_conflicts);
-            painter.extend(shapes);
-        }
 
-        // overlay selected cell
-        for key in &self.runtime_data.selected_ids {
-            let rect = get_rect_at_cell(&self.dimensions, to_screen, *key);
-            let shape =
-                Shape::rect_stroke(rect, Rounding::default(), Stroke::new(4.0, Color32::RED));
-            painter.add(shape);
-        }
-        if let Some(pivot_id) = self.runtime_data.pivot_id {
-            let rect = get_rect_at_cell(&self.dimensions, to_screen, pivot_id);
-            let shape = Shape::rect_stroke(
-                rect,
-                Rounding::default(),
-                Stroke::new(4.0, Color32::LIGHT_BLUE),
-            );
-            painter.add(shape);
+            // planned route
+            painter.extend(get_route_shapes(
+                to_screen,
+                &self.world.dimensions,
+                &self.runtime_data.route,
+            ));
+
+            // overlay selected cell
+            for key in &self.runtime_data.selected_ids {
+                let rect = get_rect_at_cell(&self.world.dimensions, to_screen, *key);
+                let shape =
+                    Shape::rect_stroke(rect, Rounding::default(), Stroke::new(4.0, Color32::RED));
+                painter.add(shape);
+            }
+            if let Some(pivot_id) = self.runtime_data.pivot_id {
+                let rect = get_rect_at_cell(&self.world.dimensions, to_screen, pivot_id);
+                let shape = Shape::rect_stroke(
+                    rect,
+                    Rounding::default(),
+                    Stroke::new(4.0, Color32::LIGHT_BLUE),
+                );
+                painter.add(shape);
+            }
         }
 
         // Responses
 
         // hover
-        if let Some(pointer_pos) = response.hover_pos() {
+        if let Some(interior) = self.interior.clone() {
+            if let Some(pointer_pos) = response.hover_pos() {
+                self.interior_tooltip(ui, &interior, from_screen * pointer_pos);
+            }
+        } else if let Some(pointer_pos) = response.hover_pos() {
             let key = self.cellkey_from_screen(from_screen, pointer_pos);
             self.runtime_data.hover_pos = key;
 
@@ -179,11 +221,18 @@ impl TemplateApp {
                 region: String::new(),
                 cell_name: String::new(),
                 conflicts: Vec::new(),
+                diff: Vec::new(),
+                seams: Vec::new(),
+                world_map: Vec::new(),
+                reference: Vec::new(),
+                doors: Vec::new(),
+                travel: Vec::new(),
+                weather: Vec::new(),
                 debug: String::new(),
             };
 
             // get cell
-            if let Some(cell) = self.cell_records.get(&key) {
+            if let Some(cell) = self.world.cell_records.get(&key) {
                 tooltipinfo.cell_name.clone_from(&cell.name);
                 if let Some(region) = cell.region.as_ref() {
                     tooltipinfo.region.clone_from(region);
@@ -199,8 +248,8 @@ impl TemplateApp {
                 let y = transformed_position.y * VERTEX_CNT as f32;
 
                 if let Some(height) = height_from_screen_space(
-                    &self.heights,
-                    &self.dimensions,
+                    &self.world.heights,
+                    &self.world.dimensions,
                     x as usize,
                     y as usize,
                 ) {
@@ -210,11 +259,126 @@ impl TemplateApp {
 
             // get conflicts
             if self.ui_data.show_tooltips {
-                if let Some(conflicts) = self.cell_conflicts.get(&key) {
+                if let Some(conflicts) = self.world.cell_conflicts.get(&key) {
                     tooltipinfo.conflicts.clone_from(conflicts);
                 }
             }
 
+            // get diff
+            if self.ui_data.overlay_diff {
+                if let Some(diff) = self.world.cell_diffs.get(&key) {
+                    tooltipinfo.diff = diff.summary();
+                }
+            }
+
+            // get seams
+            if self.ui_data.overlay_seams {
+                tooltipinfo.seams = self
+                    .world
+                    .seams
+                    .iter()
+                    .filter(|s| s.cell == key || s.neighbor == key)
+                    .map(|s| s.summary())
+                    .collect();
+            }
+
+            // get stale world map heights
+            if self.ui_data.overlay_world_map {
+                if let Some(delta) = self.world.world_map_deltas.get(&key) {
+                    let tolerance = self.ui_data.gamemap_settings.stale_tolerance;
+                    tooltipinfo
+                        .world_map
+                        .push(format!("off by up to {} steps", delta));
+                    if *delta <= tolerance {
+                        tooltipinfo.world_map.push("within tolerance".to_string());
+                    }
+                }
+            }
+
+            // get doors
+            if self.ui_data.overlay_doors {
+                let interior_name = |id: &String| {
+                    self.world
+                        .interior_records
+                        .get(id)
+                        .map(|c| c.name.clone())
+                        .unwrap_or(id.clone())
+                };
+                let mut interiors = self
+                    .world
+                    .door_entrances
+                    .iter()
+                    .filter(|e| e.cell == key)
+                    .map(|e| format!("Door to {}", interior_name(&e.interior)))
+                    .collect::<Vec<_>>();
+                interiors.sort();
+                interiors.dedup();
+                tooltipinfo.doors = interiors;
+                for connection in &self.world.door_connections {
+                    if connection.from_cell() == key {
+                        tooltipinfo.doors.push(format!(
+                            "{} exits to {}",
+                            interior_name(&connection.interior),
+                            connection.destination
+                        ));
+                    }
+                }
+            }
+
+            // get weather
+            if self.ui_data.overlay_weather {
+                if let Some(region) = get_region(&self.world.regn_records, &tooltipinfo.region) {
+                    tooltipinfo.weather = get_weather_chances(region)
+                        .iter()
+                        .map(|(weather, chance)| format!("{} {}%", weather.label(), chance))
+                        .collect();
+                }
+            }
+
+            // get travel routes
+            if self.ui_data.overlay_travel {
+                tooltipinfo.travel = self
+                    .world
+                    .travel_routes
+                    .iter()
+                    .filter(|r| r.from_cell() == key)
+                    .map(|r| r.describe(&self.world.cell_records))
+                    .collect();
+            }
+
+            // get reference
+            if self.ui_data.overlay_references {
+                if let Some((cell_key, reference_key)) = get_exterior_reference_at(
+                    to_screen,
+                    &self.world.dimensions,
+                    &self.world.cell_records,
+                    &self.world.object_types,
+                    &self.ui_data.reference_filter,
+                    key,
+                    pointer_pos,
+                ) {
+                    let reference = &self.world.cell_records[&cell_key].references[&reference_key];
+                    let plugin = self
+                        .world
+                        .reference_plugins
+                        .get(&cell_key)
+                        .and_then(|plugins| plugins.get(&reference_key))
+                        .and_then(|hash| self.world.plugins.iter().find(|p| p.hash == *hash))
+                        .map(|p| p.get_name())
+                        .unwrap_or_default();
+                    tooltipinfo.reference = vec![
+                        reference.id.clone(),
+                        plugin,
+                        format!(
+                            "({:.0}, {:.0}, {:.0})",
+                            reference.translation[0],
+                            reference.translation[1],
+                            reference.translation[2]
+                        ),
+                    ];
+                }
+            }
+
             self.runtime_data.info = tooltipinfo;
 
             if self.ui_data.show_tooltips && ui.ui_contains_pointer() {
@@ -255,6 +419,69 @@ impl TemplateApp {
                                 }
                             }
                         }
+
+                        // show changes against the diff base
+                        if !info.diff.is_empty() {
+                            ui.label("________");
+                            ui.label("Changes:");
+                            for line in info.diff {
+                                ui.label(format!("  - {}", line));
+                            }
+                        }
+
+                        // show doors into interiors
+                        if !info.doors.is_empty() {
+                            ui.label("________");
+                            ui.label("Doors:");
+                            for line in info.doors {
+                                ui.label(format!("  - {}", line));
+                            }
+                        }
+
+                        // show weather chances
+                        if !info.weather.is_empty() {
+                            ui.label("________");
+                            ui.label("Weather:");
+                            for line in info.weather {
+                                ui.label(format!("  - {}", line));
+                            }
+                        }
+
+                        // show travel routes
+                        if !info.travel.is_empty() {
+                            ui.label("________");
+                            ui.label("Travel:");
+                            for line in info.travel {
+                                ui.label(format!("  - {}", line));
+                            }
+                        }
+
+                        // show the hovered reference
+                        if !info.reference.is_empty() {
+                            ui.label("________");
+                            ui.label("Reference:");
+                            for line in info.reference {
+                                ui.label(format!("  {}", line));
+                            }
+                        }
+
+                        // show mismatched borders
+                        if !info.seams.is_empty() {
+                            ui.label("________");
+                            ui.label("Seams:");
+                            for line in info.seams {
+                                ui.label(format!("  - {}", line));
+                            }
+                        }
+
+                        // show stale world map heights
+                        if !info.world_map.is_empty() {
+                            ui.label("________");
+                            ui.label("World map (WNAM):");
+                            for line in info.world_map {
+                                ui.label(format!("  - {}", line));
+                            }
+                        }
                     },
                 );
             }
@@ -314,7 +541,7 @@ impl TemplateApp {
             ui.separator();
 
             if ui.button("Save as image").clicked() {
-                match self.save_image(ctx) {
+                match self.save_image() {
                     Ok(_) => {}
                     Err(e) => {
                         info!("Error saving image: {:?}", e);
@@ -323,19 +550,92 @@ impl TemplateApp {
 
                 ui.close_menu();
             }
+
+            if ui.button("Save as SVG").clicked() {
+                if let Err(e) = self.save_svg() {
+                    info!("Error saving svg: {:?}", e);
+                }
+
+                ui.close_menu();
+            }
+
+            // exterior only
+            if self.interior.is_some() {
+                return;
+            }
+
+            if ui.button("Export web map tiles").clicked() {
+                if let Err(e) = self.save_tiles() {
+                    info!("Error exporting tiles: {:?}", e);
+                }
+
+                ui.close_menu();
+            }
+
+            if ui.button("Export heightmap").clicked() {
+                if let Err(e) = self.save_heightmap() {
+                    info!("Error exporting heightmap: {:?}", e);
+                }
+
+                ui.close_menu();
+            }
+
+            if ui.button("Import heightmap").clicked() {
+                if let Err(e) = self.import_heightmap() {
+                    info!("Error importing heightmap: {:?}", e);
+                }
+
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            let key = self.runtime_data.hover_pos;
+            if ui.button("Route from here").clicked() {
+                self.runtime_data.route_start = Some(key);
+                self.update_route();
+                ui.close_menu();
+            }
+            if ui.button("Route to here").clicked() {
+                self.runtime_data.route_end = Some(key);
+                self.update_route();
+                ui.close_menu();
+            }
+            if ui
+                .button("Set Mark here")
+                .on_hover_text("Recall to this cell when planning routes")
+                .clicked()
+            {
+                self.runtime_data.route_mark = Some(key);
+                self.update_route();
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            if ui.button("Save seams report").clicked() {
+                if let Err(e) = self.save_seams_report() {
+                    info!("Error saving seams report: {:?}", e);
+                }
+
+                ui.close_menu();
+            }
         });
 
         // click
+        if self.interior.is_some() {
+            return;
+        }
         if let Some(interact_pos) = painter.ctx().pointer_interact_pos() {
             if ui.ctx().input(|i| i.pointer.primary_clicked()) {
                 // if in the cell panel, we select the cell
                 let key = self.cellkey_from_screen(from_screen, interact_pos);
 
                 // check if withing dimensions
-                let inside = key.0 >= self.dimensions.min_x
-                    && key.0 <= self.dimensions.max_x
-                    && key.1 >= self.dimensions.min_y
-                    && key.1 <= self.dimensions.max_y;
+                let inside = key.0 >= self.world.dimensions.min_x
+                    && key.0 <= self.world.dimensions.max_x
+                    && key.1 >= self.world.dimensions.min_y
+                    && key.1 <= self.world.dimensions.max_y;
 
                 if inside {
                     // toggle selection
@@ -392,6 +692,16 @@ impl TemplateApp {
                         } else {
                             self.runtime_data.selected_ids = vec![key];
                             self.runtime_data.pivot_id = Some(key);
+
+                            // show the cell's region in the regions panel
+                            if let Some(region) = self
+                                .world
+                                .cell_records
+                                .get(&key)
+                                .and_then(|cell| cell.region.clone())
+                            {
+                                self.runtime_data.selected_region = Some(region);
+                            }
                         }
                     }
                 }
@@ -1,4 +1,8 @@
 pub mod cell_panel;
+pub mod conflicts_panel;
+pub mod interiors_panel;
 mod map_view;
 pub mod plugins_panel;
+pub mod regions_panel;
+pub mod route_panel;
 pub mod settings;
@@ -1,16 +1,14 @@
-use std::collections::hash_map::Entry;
-
-use log::{info, warn};
-use tes3::esp::{Cell, Landscape, LandscapeTexture, Npc, Plugin, Region};
+use log::{error, warn};
 
+use crate::load_order::{ELoadOrderSource, LoadOrder};
 use crate::*;
 
 impl TemplateApp {
     pub fn plugins_panel(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         ui.heading("Plugins");
 
-        // load plugins
-        if self.plugins.is_none() && self.data_files.is_some() {
+        // load plugins, a config file has its own data directories
+        if self.plugins.is_none() && self.has_load_order() {
             self.refresh_plugins();
         }
 
@@ -27,17 +25,58 @@ impl TemplateApp {
                 if let Some(path) = folder_option {
                     self.data_files = Some(path.clone());
                     // populate plugins here
-                    let plugins = get_plugins_sorted(&path, false);
-                    let vms = plugins
-                        .iter()
-                        .map(|p| PluginViewModel::from_path(p.clone()))
-                        .collect();
-                    self.plugins = Some(vms);
+                    self.refresh_plugins();
                 }
             }
         });
 
-        if self.data_files.is_none() {
+        // load order source and config file
+        ui.horizontal(|ui| {
+            ui.label("Load order:");
+            let mut changed = false;
+            egui::ComboBox::from_id_source("load_order_source")
+                .selected_text(match self.load_order_source {
+                    ELoadOrderSource::FileTime => "File time",
+                    ELoadOrderSource::MorrowindIni => "Morrowind.ini",
+                    ELoadOrderSource::OpenMWCfg => "openmw.cfg",
+                })
+                .show_ui(ui, |ui| {
+                    for (source, label) in [
+                        (ELoadOrderSource::FileTime, "File time"),
+                        (ELoadOrderSource::MorrowindIni, "Morrowind.ini"),
+                        (ELoadOrderSource::OpenMWCfg, "openmw.cfg"),
+                    ] {
+                        if ui
+                            .selectable_value(&mut self.load_order_source, source, label)
+                            .changed()
+                        {
+                            self.load_order_config = None;
+                            changed = true;
+                        }
+                    }
+                });
+
+            if self.load_order_source != ELoadOrderSource::FileTime {
+                if ui.button("🗁").on_hover_text("Select config file").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("config", &["ini", "cfg"])
+                        .pick_file()
+                    {
+                        self.load_order_config = Some(path);
+                        changed = true;
+                    }
+                }
+                if let Some(config) = &self.load_order_config {
+                    ui.label(format!("{}", config.display()));
+                }
+            }
+
+            if changed {
+                self.refresh_plugins();
+            }
+        });
+
+        if !self.has_load_order() {
             return;
         }
 
@@ -64,14 +103,70 @@ impl TemplateApp {
             }
 
             ui.visuals_mut().override_text_color = Some(Color32::DARK_GREEN);
-            if ui.button("Load").clicked() {
-                self.load_plugin_data();
-                self.reload_background(ctx, None, true, true);
-                self.reload_paths(ctx);
+            let load_button = egui::Button::new("Load");
+            if ui.add_enabled(self.loader.is_none(), load_button).clicked() {
+                if self.plugins.is_some() {
+                    // dimensions and heights are calculated on load
+                    self.load_plugins(ctx, false);
+                } else {
+                    warn!("No plugins loaded");
+                }
             }
             ui.visuals_mut().override_text_color = None;
         });
 
+        // comparison against a "before" state
+        ui.horizontal(|ui| {
+            let button = egui::Button::new("Load as diff base");
+            if ui
+                .add_enabled(self.loader.is_none() && self.plugins.is_some(), button)
+                .on_hover_text("Load the selected plugins as the state to compare against")
+                .clicked()
+            {
+                self.load_plugins(ctx, true);
+            }
+
+            if let Some(diff_base) = &self.diff_base {
+                ui.label(format!(
+                    "{} plugins, {} cells changed",
+                    diff_base.plugins.len(),
+                    self.world.cell_diffs.len()
+                ));
+                if ui.button("Save report").clicked() {
+                    if let Err(e) = self.save_diff_report() {
+                        error!("Failed to save diff report: {}", e);
+                    }
+                }
+                if ui.button("x").on_hover_text("Clear diff base").clicked() {
+                    self.diff_base = None;
+                    self.compare_with_diff_base();
+                }
+            }
+        });
+
+        // load progress
+        if let Some(loader) = &self.loader {
+            let progress = loader.progress();
+            ui.horizontal(|ui| {
+                let text = if loader.is_cancelled() {
+                    "Cancelling...".to_owned()
+                } else {
+                    format!("{} ({}/{})", progress.message, progress.step, progress.steps)
+                };
+                ui.add(
+                    egui::ProgressBar::new(progress.fraction())
+                        .desired_width(200.0)
+                        .text(text),
+                );
+                if ui
+                    .add_enabled(!loader.is_cancelled(), egui::Button::new("Cancel"))
+                    .clicked()
+                {
+                    loader.cancel();
+                }
+            });
+        }
+
         ui.separator();
 
         // search bar
@@ -110,225 +205,36 @@ impl TemplateApp {
         }
     }
 
-    fn refresh_plugins(&mut self) {
-        // populate plugins here
-        let path = self.data_files.as_ref().unwrap();
-        let plugins = get_plugins_sorted(&path, false);
-        let vms = plugins
-            .iter()
-            .map(|p| PluginViewModel::from_path(p.clone()))
-            .collect();
-        self.plugins = Some(vms);
+    /// The file time load order needs a data files folder, the configs list their data directories
+    fn has_load_order(&self) -> bool {
+        self.data_files.is_some() || self.load_order_source != ELoadOrderSource::FileTime
     }
 
-    fn load_plugin_data(&mut self) {
-        // guarded return on self.plugins
-        if self.plugins.is_none() {
-            warn!("No plugins loaded");
+    fn refresh_plugins(&mut self) {
+        if !self.has_load_order() {
             return;
         }
 
-        // clear previous data
-        self.land_records.clear();
-        self.ltex_records.clear();
-        self.regn_records.clear();
-        self.travel_edges.clear();
-        self.cell_records.clear();
-        self.almsivi_interventions.clear();
-        self.divine_interventions.clear();
-        self.kyne_interventions.clear();
-        self.cell_conflicts.clear();
-
-        // load plugins into memory
-        let mut land_records: HashMap<CellKey, Landscape> = HashMap::default();
-
-        let mut cells: HashMap<CellKey, Cell> = HashMap::default();
-        //let mut land_id_map: HashMap<String, CellKey> = HashMap::default();
-        let mut cell_conflicts: HashMap<CellKey, Vec<u64>> = HashMap::default();
-        let mut travels: HashMap<String, (Vec<CellKey>, String)> = HashMap::default();
-        let mut npcs: HashMap<String, CellKey> = HashMap::default();
-        let mut almsivi_interventions: HashMap<CellKey, Cell> = HashMap::default();
-        let mut divine_interventions: HashMap<CellKey, Cell> = HashMap::default();
-        let mut kyne_interventions: HashMap<CellKey, Cell> = HashMap::default();
-
-        let enabled_plugins: Vec<&PluginViewModel> = self
-            .plugins
-            .as_ref()
-            .unwrap()
-            .iter()
-            .filter(|p| p.enabled)
-            .collect();
-
-        for vm in enabled_plugins {
-            let path = vm.path.clone();
-            let mut plugin = Plugin::new();
-            if plugin
-                .load_path_filtered(&path, |tag| {
-                    matches!(
-                        &tag,
-                        b"TES3" | b"LAND" | b"LTEX" | b"CELL" | b"NPC_" | b"REGN"
-                    )
-                })
-                .is_ok()
-            {
-                info!(
-                    "\t== loading plugin {} with hash {}",
-                    path.display(),
-                    vm.hash
-                );
-
-                // add travels
-                for npc in plugin.objects_of_type::<Npc>() {
-                    let travel_destinations = npc.travel_destinations.clone();
-                    if !travel_destinations.is_empty() {
-                        let mut travel_destination_cells: Vec<CellKey> = vec![];
-                        for d in travel_destinations {
-                            let mut x = (d.translation[0] / 8192.0) as i32;
-                            if x < 0 {
-                                x -= 1;
-                            }
-                            let mut y = (d.translation[1] / 8192.0) as i32;
-                            if y < 0 {
-                                y -= 1;
-                            }
-
-                            travel_destination_cells.push((x, y));
-                        }
-
-                        // get npc class
-                        let class = &npc.class;
-                        travels.insert(npc.id.clone(), (travel_destination_cells, class.clone()));
-                    }
-                }
-
-                // add Cells
-                for cell in plugin.objects_of_type::<Cell>() {
-                    if cell.is_interior() {
-                        continue;
-                    }
-
-                    let key = (cell.data.grid.0, cell.data.grid.1);
-
-                    for (npc_id, _) in travels.clone() {
-                        if cell.references.iter().any(|p| p.1.id == npc_id) {
-                            npcs.insert(npc_id, key);
-                        }
-                    }
-
-                    if let Entry::Vacant(e) = cell_conflicts.entry(key) {
-                        e.insert(vec![vm.hash]);
-                    } else {
-                        let mut value = cell_conflicts.get(&key).unwrap().to_owned();
-                        value.push(vm.hash);
-                        cell_conflicts.insert(key, value);
-                    }
-
-                    cells.insert(key, cell.clone());
-                }
-
-                // add landscape
-                for land in plugin.objects_of_type::<Landscape>() {
-                    let key = (land.grid.0, land.grid.1);
-
-                    // add landscape
-                    land_records.insert(key, land.clone());
-                    //land_id_map.insert(get_unique_id(&TES3Object::Landscape(land.clone())), key);
-                }
-
-                // add landscape textures
-                for ltex in plugin.objects_of_type::<LandscapeTexture>() {
-                    // add landscape
-                    self.ltex_records.insert(ltex.index, ltex.clone());
-                }
-
-                // add regions
-                for region in plugin.objects_of_type::<Region>() {
-                    self.regn_records.insert(region.id.clone(), region.clone());
-                }
-
-                // add almsivi interventions
-                let almsivi_static_string = "TempleMarker";
-                for cell in plugin.objects_of_type::<Cell>() {
-                    if cell.references.iter().any(|p| p.1.id == almsivi_static_string) {
-                        let coord = (cell.data.grid.0, cell.data.grid.1);
-                        almsivi_interventions.insert(coord, cell.clone());
-                    }                    
+        // populate plugins here
+        let data_files = self.data_files.as_deref();
+        let source = self.load_order_source;
+        let load_order =
+            match LoadOrder::from_source(source, self.load_order_config.as_deref(), data_files) {
+                Ok(load_order) => load_order,
+                Err(e) => {
+                    error!("Failed to read load order from {:?}: {}", source, e);
+                    LoadOrder::from_source(ELoadOrderSource::FileTime, None, data_files)
+                        .unwrap_or_default()
                 }
-                
-                // add divine interventions
-                let divine_static_string = "DivineMarker";
-                for cell in plugin.objects_of_type::<Cell>() {
-                    if cell.references.iter().any(|p| p.1.id == divine_static_string) {
-                        let coord = (cell.data.grid.0, cell.data.grid.1);
-                        // println!("divine node {} at {:?}",cell.name,coord);
+            };
 
-                        // manually handle fort frostmoth due to Bloodmoon + TotSP location clash
-                        if cell.name == "Fort Frostmoth" {
-                            let bloodmoon_coord = (-22, 17);
-                            let totsp_coord =(-15, 23); 
-                            if coord == totsp_coord {
-                                if divine_interventions.contains_key(&bloodmoon_coord) {
-                                    divine_interventions.remove(&bloodmoon_coord);
-                                }
-                                divine_interventions.insert(coord, cell.clone());
-                            }
-                            else if coord == bloodmoon_coord {
-                                if !divine_interventions.contains_key(&totsp_coord) {
-                                    divine_interventions.insert(coord, cell.clone());
-                                }
-                            }
-                        }
-                        else {
-                            divine_interventions.insert(coord, cell.clone());
-                        }
-                        
-                    }         
-                }
+        let use_omw_plugins = source == ELoadOrderSource::OpenMWCfg;
+        self.plugins = Some(load_order.get_plugins(use_omw_plugins));
 
-                // add kyne intervention - T_Aid_KyneInterventionMarker
-                let kyne_static_string = "T_Aid_KyneInterventionMarker";
-                for cell in plugin.objects_of_type::<Cell>() {
-                    if cell.references.iter().any(|p| p.1.id == kyne_static_string) {
-                        let coord = (cell.data.grid.0, cell.data.grid.1);
-                        kyne_interventions.insert(coord, cell.clone());
-                    }                    
-                }
-            }
-        }
-        
-        // travel overlay
-        let mut edges: Vec<(String, (CellKey, CellKey))> = vec![];
-        for (key, start) in npcs.clone() {
-            if let Some((dest, class)) = travels.get(&key) {
-                for d in dest {
-                    if !edges.contains(&(class.to_string(), (*d, start))) {
-                        edges.push((class.to_string(), (start, *d)));
-                    }
-                }
-            }
-        }
-        edges.dedup();
-        let mut ordered_edges = HashMap::default();
-        for (class, _pairs) in edges.iter() {
-            ordered_edges.insert(class.to_string(), vec![]);
-        }
-        for (class, pair) in edges {
-            if let Some(v) = ordered_edges.get_mut(&class) {
-                v.push(pair);
-            }
-        }
-        self.travel_edges = ordered_edges;
-        
-        // get final list of cells
-        for (k, v) in cell_conflicts.iter().filter(|p| p.1.len() > 1) {
-            self.cell_conflicts.insert(*k, v.to_vec());
-        }
-        
-        self.land_records = land_records;
-        self.cell_records = cells;
-        self.almsivi_interventions = almsivi_interventions;
-        self.divine_interventions = divine_interventions;
-        self.kyne_interventions = kyne_interventions;
-        // self.land_ids = land_id_map;
+        // textures are resolved through the same data directories and archives
+        self.vfs = Vfs::from_load_order(&load_order);
+        self.texture_map.clear();
+        self.texture_map_resolution = 0;
+        self.tiles.reset(&self.ui_data);
     }
 }
@@ -0,0 +1,140 @@
+use egui::{Color32, RichText};
+
+use crate::region::{get_region, get_region_cells, get_weather_chances};
+use crate::{get_cell_name, TemplateApp};
+
+impl TemplateApp {
+    pub fn regions_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Regions");
+
+        // search bar
+        ui.horizontal(|ui| {
+            ui.label("Filter: ");
+            ui.text_edit_singleline(&mut self.runtime_data.region_filter);
+            // clear filter button
+            if ui.button("x").clicked() {
+                self.runtime_data.region_filter.clear();
+            }
+        });
+
+        let filter = self.runtime_data.region_filter.to_lowercase();
+        let mut regions = self
+            .world
+            .regn_records
+            .values()
+            .filter(|r| {
+                filter.is_empty()
+                    || r.id.to_lowercase().contains(&filter)
+                    || r.name.to_lowercase().contains(&filter)
+            })
+            .map(|r| (r.name.clone(), r.id.clone(), r.map_color))
+            .collect::<Vec<_>>();
+        regions.sort();
+
+        egui::ScrollArea::vertical()
+            .id_source("regions_list")
+            .max_height(200.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for (name, id, color) in regions {
+                    let selected = self
+                        .runtime_data
+                        .selected_region
+                        .as_ref()
+                        .is_some_and(|r| r.eq_ignore_ascii_case(&id));
+                    let text = RichText::new(format!("■ {}", name))
+                        .color(Color32::from_rgb(color[0], color[1], color[2]));
+                    if ui
+                        .selectable_label(selected, text)
+                        .on_hover_text(&id)
+                        .clicked()
+                    {
+                        self.runtime_data.selected_region = if selected { None } else { Some(id) };
+                    }
+                }
+            });
+
+        ui.separator();
+
+        let Some(region) = self
+            .runtime_data
+            .selected_region
+            .as_ref()
+            .and_then(|id| get_region(&self.world.regn_records, id))
+            .cloned()
+        else {
+            ui.label("Select a region or click a cell on the map");
+            return;
+        };
+
+        ui.label(RichText::new(format!("{} ({})", region.name, region.id)).strong());
+
+        egui::ScrollArea::vertical()
+            .id_source("region_details")
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                egui::CollapsingHeader::new("Weather")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for (weather, chance) in get_weather_chances(&region) {
+                            ui.horizontal(|ui| {
+                                ui.label(RichText::new("■").color(weather.color()));
+                                ui.add(
+                                    egui::ProgressBar::new(chance as f32 / 100.0)
+                                        .desired_width(120.0)
+                                        .text(format!("{} {}%", weather.label(), chance)),
+                                );
+                            });
+                        }
+                    });
+
+                egui::CollapsingHeader::new(format!("Sounds ({})", region.sounds.len())).show(
+                    ui,
+                    |ui| {
+                        for sound in &region.sounds {
+                            ui.label(format!("{} {}%", sound.sound_id, sound.chance));
+                        }
+                    },
+                );
+
+                egui::CollapsingHeader::new("Sleep creatures").show(ui, |ui| {
+                    if region.sleep_creature.is_empty() {
+                        ui.label("None");
+                        return;
+                    }
+                    ui.label(&region.sleep_creature);
+                    match self
+                        .world
+                        .levc_records
+                        .get(&region.sleep_creature.to_lowercase())
+                    {
+                        Some(list) => {
+                            ui.label(format!("Chance none: {}%", list.chance_none));
+                            for (creature, level) in &list.creatures {
+                                ui.label(format!("  - {} (level {})", creature, level));
+                            }
+                        }
+                        None => {
+                            ui.label("Leveled list not found");
+                        }
+                    }
+                });
+
+                let cells = get_region_cells(&self.world.cell_records, &region.id);
+                egui::CollapsingHeader::new(format!("Cells ({})", cells.len())).show(ui, |ui| {
+                    for key in cells {
+                        let selected = self.runtime_data.selected_ids.last() == Some(&key);
+                        if ui
+                            .selectable_label(
+                                selected,
+                                get_cell_name(&self.world.cell_records, key),
+                            )
+                            .clicked()
+                        {
+                            self.runtime_data.selected_ids = vec![key];
+                        }
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,77 @@
+use egui::RichText;
+
+use crate::planner::{format_hours, EStepKind};
+use crate::{get_cell_at_translation, get_cell_name, CellKey, TemplateApp};
+
+impl TemplateApp {
+    pub fn route_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Route");
+        ui.label("Pick cells with the map context menu or use the selected cell");
+
+        let selected = self.runtime_data.selected_ids.last().copied();
+        let mut changed = false;
+        egui::Grid::new("route_cells").show(ui, |ui| {
+            let data = &mut self.runtime_data;
+            for (label, cell) in [
+                ("Start", &mut data.route_start),
+                ("End", &mut data.route_end),
+                ("Mark", &mut data.route_mark),
+            ] {
+                ui.label(label);
+                ui.label(match cell {
+                    Some(key) => get_cell_name(&self.world.cell_records, *key),
+                    None => "-".to_owned(),
+                });
+                if ui
+                    .add_enabled(selected.is_some(), egui::Button::new("Selected"))
+                    .clicked()
+                {
+                    *cell = selected;
+                    changed = true;
+                }
+                if ui.button("x").clicked() {
+                    *cell = None;
+                    changed = true;
+                }
+                ui.end_row();
+            }
+        });
+        if changed {
+            self.update_route();
+        }
+
+        ui.separator();
+
+        if self.runtime_data.route.is_empty() {
+            ui.label("No route");
+            return;
+        }
+
+        let total = self.runtime_data.route.iter().map(|s| s.hours).sum::<f32>();
+        ui.label(RichText::new(format!("Total: {}", format_hours(total))).strong());
+
+        let mut clicked: Option<CellKey> = None;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for (i, step) in self.runtime_data.route.iter().enumerate() {
+                    let mut text = RichText::new(format!(
+                        "{}. {} ({})",
+                        i + 1,
+                        step.description,
+                        format_hours(step.hours)
+                    ));
+                    // walking is drawn white on the map, which doesn't read on light panels
+                    if step.kind != EStepKind::Walk {
+                        text = text.color(step.kind.color());
+                    }
+                    if ui.selectable_label(false, text).clicked() {
+                        clicked = Some(get_cell_at_translation(step.to));
+                    }
+                }
+            });
+        if let Some(key) = clicked {
+            self.runtime_data.selected_ids = vec![key];
+        }
+    }
+}
@@ -1,6 +1,10 @@
 use egui::Ui;
 
-use crate::{EBackground, HeightmapSettings, LandscapeSettings, TemplateApp};
+use crate::interior::EObjectType;
+use crate::{
+    tiles, ContourSettings, Dimensions, EBackground, EWorldMapSource, HeightmapSettings, LabelSettings, LandscapeSettings,
+    RegionStyle, TemplateApp, TerrainSettings,
+};
 
 impl TemplateApp {
     /// Settings popup menu
@@ -57,8 +61,20 @@ impl TemplateApp {
                 {
                     clicked = true;
                 }
+                for (background, label) in [
+                    (EBackground::Hillshade, "Hillshade"),
+                    (EBackground::Slope, "Slope"),
+                    (EBackground::Aspect, "Aspect"),
+                ] {
+                    if ui
+                        .selectable_value(&mut self.ui_data.background, background, label)
+                        .clicked()
+                    {
+                        clicked = true;
+                    }
+                }
 
-                if clicked && self.background_handle.is_some() {
+                if clicked && !self.world.heights.is_empty() {
                     self.reload_background(ctx, None, false, false);
                 }
             });
@@ -92,18 +108,74 @@ impl TemplateApp {
             self.reload_paths(ctx);
         }
         ui.checkbox(&mut self.ui_data.overlay_region, "Show regions");
+        if self.ui_data.overlay_region {
+            self.region_style_ui(ui);
+        }
+        ui.checkbox(&mut self.ui_data.overlay_weather, "Show dominant weather");
+        ui.checkbox(&mut self.ui_data.overlay_contours, "Show contour lines");
+        ui.checkbox(&mut self.ui_data.overlay_coastline, "Show coastline");
+        if self.ui_data.overlay_contours || self.ui_data.overlay_coastline {
+            self.contour_settings_ui(ui);
+        }
         ui.checkbox(&mut self.ui_data.overlay_grid, "Show cell grid");
         ui.checkbox(&mut self.ui_data.overlay_cities, "Show cities");
+        ui.checkbox(&mut self.ui_data.overlay_labels, "Show place names");
+        if self.ui_data.overlay_labels {
+            self.label_settings_ui(ui);
+        }
         ui.checkbox(&mut self.ui_data.overlay_alm_interventions, "Show almsivi intervention");
         ui.checkbox(&mut self.ui_data.overlay_div_interventions, "Show divine intervention");
         ui.checkbox(&mut self.ui_data.overlay_kyn_interventions, "Show kyne intervention");
         ui.checkbox(&mut self.ui_data.overlay_travel, "Show travel");
+        if self.ui_data.overlay_travel {
+            self.travel_colors(ui);
+        }
         ui.checkbox(&mut self.ui_data.overlay_conflicts, "Show conflicts");
+        ui.checkbox(&mut self.ui_data.overlay_seams, "Show landscape seams");
+        ui.checkbox(&mut self.ui_data.overlay_world_map, "Show stale world map");
+        if self.ui_data.overlay_world_map {
+            ui.add(
+                egui::Slider::new(&mut self.ui_data.gamemap_settings.stale_tolerance, 0..=16)
+                    .text("Tolerance"),
+            )
+            .on_hover_text("Allowed difference in world map steps, 128 units above sea level and 16 below");
+        }
+        ui.checkbox(&mut self.ui_data.overlay_doors, "Show doors");
+        ui.checkbox(&mut self.ui_data.overlay_references, "Show references");
+        if self.ui_data.overlay_references {
+            let filter = &mut self.ui_data.reference_filter;
+            ui.horizontal_wrapped(|ui| {
+                for object_type in EObjectType::ALL {
+                    if object_type == EObjectType::Other {
+                        continue;
+                    }
+                    let mut enabled = filter.types.contains(&object_type);
+                    if ui
+                        .checkbox(&mut enabled, object_type.tag())
+                        .on_hover_text(object_type.label())
+                        .changed()
+                    {
+                        if enabled {
+                            filter.types.push(object_type);
+                        } else {
+                            filter.types.retain(|t| *t != object_type);
+                        }
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Id: ");
+                ui.text_edit_singleline(&mut filter.id);
+            });
+        }
+        if !self.world.cell_diffs.is_empty() {
+            ui.checkbox(&mut self.ui_data.overlay_diff, "Show diff");
+        }
 
         ui.checkbox(&mut self.ui_data.show_tooltips, "Show tooltips");
 
         // settings
-        if self.background_handle.is_some() {
+        if !self.world.heights.is_empty() {
             ui.separator();
             ui.horizontal(|ui| {
                 // if reset then also refresh
@@ -113,6 +185,10 @@ impl TemplateApp {
                         self.ui_data.landscape_settings = LandscapeSettings::default();
                     } else if self.ui_data.background == EBackground::HeightMap {
                         self.ui_data.heightmap_settings = HeightmapSettings::default();
+                    } else if self.ui_data.background.is_terrain() {
+                        self.ui_data.terrain_settings = TerrainSettings::default();
+                    } else if self.ui_data.background == EBackground::GameMap {
+                        self.ui_data.gamemap_settings.source = EWorldMapSource::default();
                     }
 
                     self.reload_background(ctx, None, false, false);
@@ -135,6 +211,18 @@ impl TemplateApp {
         } else if self.ui_data.background == EBackground::HeightMap {
             ui.separator();
             self.heightmap_settings_ui(ui, ctx);
+        } else if self.ui_data.background == EBackground::GameMap {
+            ui.separator();
+            self.gamemap_settings_ui(ui, ctx);
+        }
+        if self.ui_data.background.is_terrain()
+            || matches!(
+                self.ui_data.background,
+                EBackground::GameMap | EBackground::Landscape
+            )
+        {
+            ui.separator();
+            self.terrain_settings_ui(ui, ctx);
         }
 
         // overlay settings
@@ -151,7 +239,13 @@ impl TemplateApp {
         ui.checkbox(&mut self.ui_data.realtime_update, "Realtime update");
 
         let max_texture_side = ctx.input(|i| i.max_texture_side);
-        let max_texture_resolution = self.dimensions.get_max_texture_resolution(max_texture_side);
+        // the landscape is drawn in tiles, so only one tile has to fit into a texture
+        let tile = Dimensions {
+            max_x: tiles::TILE_CELLS - 1,
+            max_y: tiles::TILE_CELLS - 1,
+            ..Default::default()
+        };
+        let max_texture_resolution = tile.get_max_texture_resolution(max_texture_side);
 
         ui.add(
             egui::Slider::new(
@@ -225,4 +319,185 @@ impl TemplateApp {
             self.reload_background(ctx, None, false, false);
         }
     }
+
+    /// Where the game map takes its heights from
+    fn gamemap_settings_ui(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        let settings = &mut self.ui_data.gamemap_settings;
+        ui.label("Game map settings");
+
+        let mut changed = false;
+        for source in [EWorldMapSource::Wnam, EWorldMapSource::VertexHeights] {
+            changed |= ui
+                .radio_value(&mut settings.source, source, source.label())
+                .changed();
+        }
+
+        if changed {
+            // reload background
+            self.reload_background(ctx, None, false, false);
+        }
+    }
+
+    /// Sun of the hillshade, slope colors and the hillshade under the game map and landscape
+    fn terrain_settings_ui(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        let background = self.ui_data.background;
+        let settings = &mut self.ui_data.terrain_settings;
+        ui.label("Terrain settings");
+
+        ui.checkbox(&mut self.ui_data.realtime_update, "Realtime update");
+
+        let mut changed = false;
+        if !background.is_terrain() {
+            changed |= ui
+                .add(egui::Slider::new(&mut settings.hillshade_blend, 0.0..=1.0).text("Hillshade"))
+                .changed();
+        }
+        if background == EBackground::Slope {
+            changed |= ui
+                .add(egui::Slider::new(&mut settings.max_slope, 5.0..=90.0).text("Max slope"))
+                .changed();
+        } else if background != EBackground::Aspect {
+            changed |= ui
+                .add(egui::Slider::new(&mut settings.azimuth, 0.0..=360.0).text("Sun azimuth"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut settings.altitude, 0.0..=90.0).text("Sun altitude"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut settings.z_factor, 0.1..=10.0).text("Exaggeration"))
+                .changed();
+        }
+
+        if changed && self.ui_data.realtime_update {
+            // reload background
+            self.reload_background(ctx, None, false, false);
+        }
+    }
+
+    /// Editable class to color table of the travel overlay
+    fn travel_colors(&mut self, ui: &mut Ui) {
+        let settings = &mut self.ui_data.travel_settings;
+        egui::CollapsingHeader::new("Travel colors").show(ui, |ui| {
+            let mut remove = None;
+            for (i, (class, color)) in settings.class_colors.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_srgba(color);
+                    ui.text_edit_singleline(class);
+                    if ui.button("x").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                settings.class_colors.remove(i);
+            }
+
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba(&mut settings.default_color);
+                ui.label("Other classes");
+            });
+
+            // classes of the loaded routes without a color yet
+            let mut missing = self
+                .world
+                .travel_routes
+                .iter()
+                .map(|r| r.class.clone())
+                .filter(|c| {
+                    !settings
+                        .class_colors
+                        .iter()
+                        .any(|(class, _)| class.eq_ignore_ascii_case(c))
+                })
+                .collect::<Vec<_>>();
+            missing.sort();
+            missing.dedup();
+            ui.horizontal_wrapped(|ui| {
+                for class in missing {
+                    if ui.button(format!("+ {}", class)).clicked() {
+                        settings.class_colors.push((class, settings.default_color));
+                    }
+                }
+                if ui.button("+").on_hover_text("Add a class").clicked() {
+                    settings
+                        .class_colors
+                        .push((String::new(), settings.default_color));
+                }
+            });
+        });
+    }
+
+    /// Fill, borders and labels of the regions overlay
+    fn region_style_ui(&mut self, ui: &mut Ui) {
+        let style = &mut self.ui_data.region_style;
+        egui::CollapsingHeader::new("Region style").show(ui, |ui| {
+            ui.add(egui::Slider::new(&mut style.fill_opacity, 0.0..=1.0).text("Fill opacity"));
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut style.borders, "Borders");
+                ui.color_edit_button_srgba(&mut style.border_color);
+                ui.add(egui::DragValue::new(&mut style.border_width).range(0.5..=8.0).speed(0.1));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut style.labels, "Labels");
+                ui.color_edit_button_srgba(&mut style.label_color);
+                ui.add(egui::DragValue::new(&mut style.label_size).range(6.0..=64.0));
+            });
+            if ui.button("Reset").clicked() {
+                *style = RegionStyle::default();
+            }
+        });
+    }
+
+    /// Interval and strokes of the contour lines and the coastline
+    fn contour_settings_ui(&mut self, ui: &mut Ui) {
+        let settings = &mut self.ui_data.contour_settings;
+        egui::CollapsingHeader::new("Contour style").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Interval");
+                ui.add(egui::DragValue::new(&mut settings.interval).range(8.0..=4096.0));
+                ui.label("Index every");
+                ui.add(egui::DragValue::new(&mut settings.index_every).range(1..=20));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Lines");
+                ui.color_edit_button_srgba(&mut settings.color);
+                ui.add(egui::DragValue::new(&mut settings.width).range(0.5..=8.0).speed(0.1));
+                ui.add(egui::DragValue::new(&mut settings.index_width).range(0.5..=8.0).speed(0.1));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.labels, "Height labels");
+                ui.add(egui::DragValue::new(&mut settings.label_size).range(6.0..=32.0));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Coastline");
+                ui.color_edit_button_srgba(&mut settings.coastline_color);
+                ui.add(
+                    egui::DragValue::new(&mut settings.coastline_width)
+                        .range(0.5..=8.0)
+                        .speed(0.1),
+                );
+            });
+            if ui.button("Reset").clicked() {
+                *settings = ContourSettings::default();
+            }
+        });
+    }
+
+    /// Font size and color of the place names overlay
+    fn label_settings_ui(&mut self, ui: &mut Ui) {
+        let settings = &mut self.ui_data.label_settings;
+        egui::CollapsingHeader::new("Place name style").show(ui, |ui| {
+            ui.add(egui::Slider::new(&mut settings.scale, 0.05..=1.0).text("Size per cell"));
+            ui.horizontal(|ui| {
+                ui.label("Min");
+                ui.add(egui::DragValue::new(&mut settings.min_size).range(6.0..=64.0));
+                ui.label("Max");
+                ui.add(egui::DragValue::new(&mut settings.max_size).range(6.0..=128.0));
+                ui.color_edit_button_srgba(&mut settings.color);
+            });
+            if ui.button("Reset").clicked() {
+                *settings = LabelSettings::default();
+            }
+        });
+    }
 }
@@ -0,0 +1,912 @@
+use std::collections::{hash_map::Entry, HashMap};
+use std::path::Path;
+
+use egui::{epaint::Fonts, pos2, ColorImage, Rect, Shape};
+use image::{imageops, ImageError, RgbaImage};
+use log::{info, warn};
+use tes3::esp::{
+    Activator, Cell, Container, Creature, Door, Header, Landscape, LandscapeTexture,
+    LeveledCreature, Light, Npc, Plugin, Region, Static,
+};
+
+use crate::background::{
+    gamemap::{generate_map, get_world_map_delta},
+    heightmap::generate_heightmap,
+    landscape::{compute_landscape_image, load_texture_map},
+    ptmap::generate_ptmap,
+    terrain::{blend_hillshade, generate_aspect_map, generate_hillshade, generate_slope_map},
+};
+use crate::conflicts::{get_plugin_edit, PluginEdit};
+use crate::diff::{diff_cells, diff_landscapes, diff_worlds, CellDiff};
+use crate::doors::{find_doors, DoorConnection, DoorEntrance, InteriorHomes};
+use crate::export::raster::{draw_shapes, export_fonts};
+use crate::interior::{get_interior_bounds, get_interior_layers, EObjectType};
+use crate::overlay::{self, paths::get_overlay_path_image};
+use crate::seams::{find_seams, Seam};
+use crate::travel::{add_travel_services, find_travel_routes, TravelRoute, TravelService};
+use crate::*;
+
+/// All overlays that can be drawn on top of a background
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EOverlay {
+    Paths,
+    Regions,
+    Weather,
+    Contours,
+    Coastline,
+    Grid,
+    AlmsiviInterventions,
+    DivineInterventions,
+    KyneInterventions,
+    Cities,
+    Travel,
+    Conflicts,
+    Diff,
+    Seams,
+    References,
+    Doors,
+    Labels,
+    WorldMap,
+}
+
+impl EOverlay {
+    /// All overlays in drawing order
+    pub const ALL: [EOverlay; 18] = [
+        EOverlay::Paths,
+        EOverlay::Regions,
+        EOverlay::Weather,
+        EOverlay::Contours,
+        EOverlay::Coastline,
+        EOverlay::Grid,
+        EOverlay::AlmsiviInterventions,
+        EOverlay::DivineInterventions,
+        EOverlay::KyneInterventions,
+        EOverlay::Cities,
+        EOverlay::Travel,
+        EOverlay::Conflicts,
+        EOverlay::Diff,
+        EOverlay::Seams,
+        EOverlay::WorldMap,
+        EOverlay::References,
+        EOverlay::Doors,
+        EOverlay::Labels,
+    ];
+
+    /// Short name, used on the command line and as svg layer id
+    pub fn id(&self) -> &'static str {
+        match self {
+            EOverlay::Paths => "paths",
+            EOverlay::Regions => "regions",
+            EOverlay::Weather => "weather",
+            EOverlay::Contours => "contours",
+            EOverlay::Coastline => "coastline",
+            EOverlay::Grid => "grid",
+            EOverlay::AlmsiviInterventions => "almsivi",
+            EOverlay::DivineInterventions => "divine",
+            EOverlay::KyneInterventions => "kyne",
+            EOverlay::Cities => "cities",
+            EOverlay::Travel => "travel",
+            EOverlay::Conflicts => "conflicts",
+            EOverlay::Diff => "diff",
+            EOverlay::Seams => "seams",
+            EOverlay::References => "references",
+            EOverlay::Doors => "doors",
+            EOverlay::Labels => "labels",
+            EOverlay::WorldMap => "wnam",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EOverlay::Paths => "Paths",
+            EOverlay::Regions => "Regions",
+            EOverlay::Weather => "Dominant weather",
+            EOverlay::Contours => "Contour lines",
+            EOverlay::Coastline => "Coastline",
+            EOverlay::Grid => "Cell grid",
+            EOverlay::AlmsiviInterventions => "Almsivi intervention",
+            EOverlay::DivineInterventions => "Divine intervention",
+            EOverlay::KyneInterventions => "Kyne intervention",
+            EOverlay::Cities => "Cities",
+            EOverlay::Travel => "Travel",
+            EOverlay::Conflicts => "Conflicts",
+            EOverlay::Diff => "Diff",
+            EOverlay::Seams => "Landscape seams",
+            EOverlay::References => "References",
+            EOverlay::Doors => "Doors",
+            EOverlay::Labels => "Place names",
+            EOverlay::WorldMap => "Stale world map",
+        }
+    }
+}
+
+/// Everything loaded from a list of plugins, independent of any GUI
+#[derive(Debug, Clone, Default)]
+pub struct WorldData {
+    /// the enabled plugins in load order
+    pub plugins: Vec<PluginViewModel>,
+
+    pub dimensions: Dimensions,
+    pub heights: Vec<f32>,
+
+    pub land_records: HashMap<CellKey, Landscape>,
+    pub ltex_records: HashMap<u32, LandscapeTexture>,
+    pub regn_records: HashMap<String, Region>,
+    /// leveled creature lists by lowercase id
+    pub levc_records: HashMap<String, LeveledCreature>,
+    pub cell_records: HashMap<CellKey, Cell>,
+    /// interior cells by lowercase name, references of all plugins merged
+    pub interior_records: HashMap<String, Cell>,
+    /// type of every referenceable object by lowercase id
+    pub object_types: HashMap<String, EObjectType>,
+
+    // intervention spells
+    pub almsivi_interventions: HashMap<CellKey, Cell>,
+    pub divine_interventions: HashMap<CellKey, Cell>,
+    pub kyne_interventions: HashMap<CellKey, Cell>,
+
+    // overlays
+    /// destinations of all placed travel NPCs and creatures
+    pub travel_routes: Vec<TravelRoute>,
+    pub cell_conflicts: HashMap<CellKey, Vec<u64>>,
+    /// the last plugin to change each reference, see get_reference_key
+    pub reference_plugins: HashMap<CellKey, HashMap<(u32, u32), u64>>,
+    /// CELL and LAND records of every plugin per cell, in load order
+    pub cell_edits: HashMap<CellKey, Vec<PluginEdit>>,
+    /// changes against a "before" world, see compare_with
+    pub cell_diffs: HashMap<CellKey, CellDiff>,
+    /// mismatched borders between adjacent landscapes
+    pub seams: Vec<Seam>,
+    /// cells whose stored world map heights (WNAM) differ from their vertex heights, see get_world_map_delta
+    pub world_map_deltas: HashMap<CellKey, u8>,
+    /// exterior doors into interiors and exits into other exterior cells
+    pub door_entrances: Vec<DoorEntrance>,
+    pub door_connections: Vec<DoorConnection>,
+    pub interior_homes: InteriorHomes,
+}
+
+impl WorldData {
+    /// Load the given plugins in order, then calculate dimensions and heights
+    pub fn from_paths<P>(paths: &[P]) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let plugins = paths
+            .iter()
+            .map(|p| {
+                let mut vm = PluginViewModel::from_path(p.as_ref().to_path_buf());
+                vm.enabled = true;
+                vm
+            })
+            .collect::<Vec<_>>();
+
+        Self::from_plugins(&plugins)
+    }
+
+    /// Load all enabled plugins in order, then calculate dimensions and heights
+    pub fn from_plugins(plugins: &[PluginViewModel]) -> Self {
+        Self::from_plugins_with_progress(plugins, |_, _, _| true).unwrap_or_default()
+    }
+
+    /// Load all enabled plugins in order, then calculate dimensions and heights.
+    /// progress is called with (index, count, plugin) before each plugin is loaded,
+    /// returning false cancels loading
+    pub fn from_plugins_with_progress<F>(
+        plugins: &[PluginViewModel],
+        mut progress: F,
+    ) -> Option<Self>
+    where
+        F: FnMut(usize, usize, &PluginViewModel) -> bool,
+    {
+        let mut world = WorldData {
+            plugins: plugins.iter().filter(|p| p.enabled).cloned().collect(),
+            ..Default::default()
+        };
+        let count = world.plugins.len();
+
+        // load plugins into memory
+        let mut cell_conflicts: HashMap<CellKey, Vec<u64>> = HashMap::default();
+        let mut travel_services: HashMap<String, TravelService> = HashMap::default();
+
+        for (i, vm) in world.plugins.iter().enumerate() {
+            if !progress(i, count, vm) {
+                info!("Loading cancelled");
+                return None;
+            }
+
+            let path = vm.path.clone();
+            let mut plugin = Plugin::new();
+            if plugin
+                .load_path_filtered(&path, |tag| {
+                    matches!(
+                        &tag,
+                        b"TES3"
+                            | b"LAND"
+                            | b"LTEX"
+                            | b"CELL"
+                            | b"NPC_"
+                            | b"REGN"
+                            | b"CREA"
+                            | b"DOOR"
+                            | b"LIGH"
+                            | b"CONT"
+                            | b"ACTI"
+                            | b"STAT"
+                            | b"LEVC"
+                    )
+                })
+                .is_ok()
+            {
+                info!(
+                    "\t== loading plugin {} with hash {}",
+                    path.display(),
+                    vm.hash
+                );
+
+                // add travels
+                add_travel_services(&mut travel_services, &plugin, &vm.get_name());
+
+                // add object types
+                let object_types = &mut world.object_types;
+                let mut add_types = |ids: Vec<&String>, object_type: EObjectType| {
+                    for id in ids {
+                        object_types.insert(id.to_lowercase(), object_type);
+                    }
+                };
+                add_types(
+                    plugin.objects_of_type::<Static>().map(|o| &o.id).collect(),
+                    EObjectType::Static,
+                );
+                add_types(
+                    plugin.objects_of_type::<Activator>().map(|o| &o.id).collect(),
+                    EObjectType::Activator,
+                );
+                add_types(
+                    plugin.objects_of_type::<Container>().map(|o| &o.id).collect(),
+                    EObjectType::Container,
+                );
+                add_types(
+                    plugin.objects_of_type::<Light>().map(|o| &o.id).collect(),
+                    EObjectType::Light,
+                );
+                add_types(
+                    plugin.objects_of_type::<Door>().map(|o| &o.id).collect(),
+                    EObjectType::Door,
+                );
+                add_types(
+                    plugin.objects_of_type::<Creature>().map(|o| &o.id).collect(),
+                    EObjectType::Creature,
+                );
+                add_types(
+                    plugin.objects_of_type::<Npc>().map(|o| &o.id).collect(),
+                    EObjectType::Npc,
+                );
+
+                // references are keyed by the name of the plugin that added them
+                let origin = get_plugin_origin(&vm.get_name());
+                let masters = plugin
+                    .objects_of_type::<Header>()
+                    .flat_map(|header| header.masters.iter())
+                    .map(|(name, _)| get_plugin_origin(name))
+                    .collect::<Vec<_>>();
+
+                // add Cells
+                for cell in plugin.objects_of_type::<Cell>() {
+                    if cell.is_interior() {
+                        let id = cell.name.to_lowercase();
+                        let merged =
+                            merge_cell(world.interior_records.get(&id), cell, origin, &masters);
+                        world.interior_records.insert(id, merged);
+                        continue;
+                    }
+
+                    let key = (cell.data.grid.0, cell.data.grid.1);
+
+                    if let Entry::Vacant(e) = cell_conflicts.entry(key) {
+                        e.insert(vec![vm.hash]);
+                    } else {
+                        let mut value = cell_conflicts.get(&key).unwrap().to_owned();
+                        value.push(vm.hash);
+                        cell_conflicts.insert(key, value);
+                    }
+
+                    let merged =
+                        merge_cell(world.cell_records.get(&key), cell, origin, &masters);
+                    let reference_plugins = world.reference_plugins.entry(key).or_default();
+                    for reference_key in cell.references.keys() {
+                        let reference_key =
+                            get_reference_key(*reference_key, origin, &masters);
+                        reference_plugins.insert(reference_key, vm.hash);
+                    }
+
+                    let edit = get_plugin_edit(&mut world.cell_edits, key, vm.hash);
+                    edit.cell = true;
+                    diff_cells(&mut edit.diff, world.cell_records.get(&key), Some(&merged));
+
+                    world.cell_records.insert(key, merged);
+                }
+
+                // add landscape textures, before the landscape that is painted with them.
+                // the landscape of earlier plugins keeps the textures it was painted with
+                let previous_ltex_records = plugin
+                    .objects_of_type::<LandscapeTexture>()
+                    .next()
+                    .map(|_| world.ltex_records.clone());
+                for ltex in plugin.objects_of_type::<LandscapeTexture>() {
+                    // add landscape
+                    world.ltex_records.insert(ltex.index, ltex.clone());
+                }
+
+                // add landscape
+                for land in plugin.objects_of_type::<Landscape>() {
+                    let key = (land.grid.0, land.grid.1);
+
+                    let edit = get_plugin_edit(&mut world.cell_edits, key, vm.hash);
+                    edit.land = true;
+                    diff_landscapes(
+                        &mut edit.diff,
+                        (
+                            world.land_records.get(&key),
+                            previous_ltex_records.as_ref().unwrap_or(&world.ltex_records),
+                        ),
+                        (Some(land), &world.ltex_records),
+                    );
+
+                    // add landscape
+                    world.land_records.insert(key, land.clone());
+                }
+
+                // add regions
+                for region in plugin.objects_of_type::<Region>() {
+                    world.regn_records.insert(region.id.clone(), region.clone());
+                }
+
+                // add leveled creatures, for the sleep creatures of regions
+                for list in plugin.objects_of_type::<LeveledCreature>() {
+                    world.levc_records.insert(list.id.to_lowercase(), list.clone());
+                }
+
+                // add almsivi interventions
+                let almsivi_static_string = "TempleMarker";
+                for cell in plugin.objects_of_type::<Cell>() {
+                    if cell.references.iter().any(|p| p.1.id == almsivi_static_string) {
+                        let coord = (cell.data.grid.0, cell.data.grid.1);
+                        world.almsivi_interventions.insert(coord, cell.clone());
+                    }
+                }
+
+                // add divine interventions
+                let divine_static_string = "DivineMarker";
+                let divine_interventions = &mut world.divine_interventions;
+                for cell in plugin.objects_of_type::<Cell>() {
+                    if cell.references.iter().any(|p| p.1.id == divine_static_string) {
+                        let coord = (cell.data.grid.0, cell.data.grid.1);
+                        // println!("divine node {} at {:?}",cell.name,coord);
+
+                        // manually handle fort frostmoth due to Bloodmoon + TotSP location clash
+                        if cell.name == "Fort Frostmoth" {
+                            let bloodmoon_coord = (-22, 17);
+                            let totsp_coord =(-15, 23);
+                            if coord == totsp_coord {
+                                if divine_interventions.contains_key(&bloodmoon_coord) {
+                                    divine_interventions.remove(&bloodmoon_coord);
+                                }
+                                divine_interventions.insert(coord, cell.clone());
+                            }
+                            else if coord == bloodmoon_coord {
+                                if !divine_interventions.contains_key(&totsp_coord) {
+                                    divine_interventions.insert(coord, cell.clone());
+                                }
+                            }
+                        }
+                        else {
+                            divine_interventions.insert(coord, cell.clone());
+                        }
+
+                    }
+                }
+
+                // add kyne intervention - T_Aid_KyneInterventionMarker
+                let kyne_static_string = "T_Aid_KyneInterventionMarker";
+                for cell in plugin.objects_of_type::<Cell>() {
+                    if cell.references.iter().any(|p| p.1.id == kyne_static_string) {
+                        let coord = (cell.data.grid.0, cell.data.grid.1);
+                        world.kyne_interventions.insert(coord, cell.clone());
+                    }
+                }
+            } else {
+                warn!("Failed to load plugin {}", path.display());
+            }
+        }
+
+        // get final list of cells
+        for (k, v) in cell_conflicts.iter().filter(|p| p.1.len() > 1) {
+            world.cell_conflicts.insert(*k, v.to_vec());
+        }
+
+        world.seams = find_seams(&world.land_records, &world.cell_edits, &world.plugins);
+        world.world_map_deltas = world
+            .land_records
+            .iter()
+            .filter_map(|(key, land)| Some((*key, get_world_map_delta(land)?)))
+            .filter(|(_, delta)| *delta > 0)
+            .collect();
+        (
+            world.door_entrances,
+            world.interior_homes,
+            world.door_connections,
+        ) = find_doors(&world.cell_records, &world.interior_records);
+        world.travel_routes = find_travel_routes(
+            &travel_services,
+            &world.cell_records,
+            &world.interior_records,
+            &world.interior_homes,
+        );
+
+        world.recalculate(None, true, true);
+        Some(world)
+    }
+
+    /// Compare this world against an older version and store the changed cells in cell_diffs
+    pub fn compare_with(&mut self, before: &WorldData) {
+        self.cell_diffs = diff_worlds(before, self);
+        info!("{} cells changed", self.cell_diffs.len());
+    }
+
+    /// Assigns dimensions and heights.
+    /// Returns false if no dimensions could be calculated
+    pub fn recalculate(
+        &mut self,
+        new_dimensions: Option<Dimensions>,
+        recalculate_dimensions: bool,
+        recalculate_heights: bool,
+    ) -> bool {
+        // calculate dimensions
+        if let Some(dimensions) = new_dimensions {
+            self.dimensions = dimensions;
+        } else if recalculate_dimensions {
+            if let Some(dims) = calculate_dimensions(&self.dimensions, &self.land_records) {
+                self.dimensions = dims;
+            } else {
+                return false;
+            }
+        }
+
+        // calculate heights
+        if recalculate_heights {
+            if let Some(heights) = calculate_heights(&self.land_records, &mut self.dimensions) {
+                self.heights = heights;
+            }
+        }
+
+        true
+    }
+
+    /// Load and resize all landscape textures used inside the current dimensions
+    pub fn load_texture_map(&self, vfs: &Vfs, texture_size: usize) -> HashMap<String, ImageBuffer> {
+        let mut texture_map: HashMap<String, ImageBuffer> = HashMap::default();
+        load_texture_map(
+            vfs,
+            &self.dimensions,
+            &self.land_records,
+            &self.ltex_records,
+            texture_size,
+            &mut texture_map,
+        );
+        texture_map
+    }
+
+    /// Generate a background as ColorImage.
+    /// The Landscape background needs a texture map from load_texture_map
+    pub fn get_background_image(
+        &self,
+        background: EBackground,
+        settings: &SavedData,
+        texture_map: &HashMap<String, ImageBuffer>,
+    ) -> Option<ColorImage> {
+        let terrain = &settings.terrain_settings;
+        match background {
+            EBackground::None => None,
+            EBackground::Landscape => {
+                let mut image = compute_landscape_image(
+                    &settings.landscape_settings,
+                    &self.dimensions,
+                    &self.land_records,
+                    &self.ltex_records,
+                    &self.heights,
+                    texture_map,
+                );
+                blend_hillshade(
+                    &mut image,
+                    &self.dimensions,
+                    &self.heights,
+                    &self.dimensions,
+                    terrain,
+                );
+                Some(image)
+            }
+            EBackground::HeightMap => Some(generate_heightmap(
+                &self.heights,
+                &self.dimensions,
+                &settings.heightmap_settings,
+            )),
+            EBackground::GameMap => {
+                let mut image = generate_map(
+                    &self.dimensions,
+                    &self.land_records,
+                    settings.gamemap_settings.source,
+                );
+                blend_hillshade(
+                    &mut image,
+                    &self.dimensions,
+                    &self.heights,
+                    &self.dimensions,
+                    terrain,
+                );
+                Some(image)
+            }
+            EBackground::PTMap => Some(generate_ptmap(&self.dimensions, &self.plugins)),
+            EBackground::Hillshade => Some(generate_hillshade(
+                &self.heights,
+                &self.dimensions,
+                terrain,
+            )),
+            EBackground::Slope => Some(generate_slope_map(
+                &self.heights,
+                &self.dimensions,
+                terrain,
+            )),
+            EBackground::Aspect => Some(generate_aspect_map(&self.heights, &self.dimensions)),
+        }
+    }
+
+    /// Render a background to an image
+    pub fn render_background(
+        &self,
+        background: EBackground,
+        settings: &SavedData,
+        texture_map: &HashMap<String, ImageBuffer>,
+    ) -> Result<Option<RgbaImage>, ImageError> {
+        match self.get_background_image(background, settings, texture_map) {
+            Some(image) => Ok(Some(color_image_to_rgba_image(&image)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the vector shapes of an overlay. The paths overlay is an image and has no shapes.
+    /// Labels are laid out with fonts, see export_fonts for images
+    pub fn get_overlay_shapes(
+        &self,
+        overlay: EOverlay,
+        to_screen: RectTransform,
+        settings: &SavedData,
+        intervention_engine: &str,
+        fonts: &Fonts,
+    ) -> Vec<Shape> {
+        let dimensions = &self.dimensions;
+        match overlay {
+            EOverlay::Paths => vec![],
+            EOverlay::Regions => overlay::regions::get_region_shapes(
+                to_screen,
+                dimensions,
+                &self.regn_records,
+                &self.cell_records,
+                &settings.region_style,
+                fonts,
+            ),
+            EOverlay::Weather => overlay::weather::get_weather_shapes(
+                to_screen,
+                dimensions,
+                &self.regn_records,
+                &self.cell_records,
+            ),
+            EOverlay::Contours | EOverlay::Coastline | EOverlay::References => {
+                self.get_visible_overlay_shapes(
+                    overlay,
+                    to_screen,
+                    *to_screen.to(),
+                    settings,
+                    intervention_engine,
+                    fonts,
+                )
+            }
+            EOverlay::Grid => overlay::grid::get_grid_shapes(to_screen, dimensions),
+            EOverlay::AlmsiviInterventions => overlay::interventions::get_intervention_shapes(
+                to_screen,
+                dimensions,
+                &self.almsivi_interventions,
+                &self.cell_records,
+                "almsivi",
+                intervention_engine,
+            ),
+            EOverlay::DivineInterventions => overlay::interventions::get_intervention_shapes(
+                to_screen,
+                dimensions,
+                &self.divine_interventions,
+                &self.cell_records,
+                "divine",
+                intervention_engine,
+            ),
+            EOverlay::KyneInterventions => overlay::interventions::get_intervention_shapes(
+                to_screen,
+                dimensions,
+                &self.kyne_interventions,
+                &self.cell_records,
+                "kyne",
+                intervention_engine,
+            ),
+            EOverlay::Cities => {
+                overlay::cities::get_cities_shapes(to_screen, dimensions, &self.cell_records)
+            }
+            EOverlay::Travel => {
+                overlay::travel::get_travel_shapes(
+                    to_screen,
+                    dimensions,
+                    &self.travel_routes,
+                    &settings.travel_settings,
+                )
+            }
+            EOverlay::Conflicts => {
+                overlay::conflicts::get_conflict_shapes(to_screen, dimensions, &self.cell_conflicts)
+            }
+            EOverlay::Diff => {
+                overlay::diff::get_diff_shapes(to_screen, dimensions, &self.cell_diffs)
+            }
+            EOverlay::Seams => overlay::seams::get_seams_shapes(to_screen, dimensions, &self.seams),
+            EOverlay::WorldMap => overlay::wnam::get_world_map_shapes(
+                to_screen,
+                dimensions,
+                &self.world_map_deltas,
+                settings.gamemap_settings.stale_tolerance,
+            ),
+            EOverlay::Doors => overlay::doors::get_door_shapes(
+                to_screen,
+                dimensions,
+                &self.door_entrances,
+                &self.door_connections,
+            ),
+            EOverlay::Labels => overlay::labels::get_place_label_shapes(
+                to_screen,
+                dimensions,
+                &self.cell_records,
+                &settings.label_settings,
+                fonts,
+            ),
+        }
+    }
+
+    /// Like get_overlay_shapes, but the overlays traced from the vertex heights and the references
+    /// only cover the cells inside clip, e.g. the visible part of the map view
+    pub fn get_visible_overlay_shapes(
+        &self,
+        overlay: EOverlay,
+        to_screen: RectTransform,
+        clip: Rect,
+        settings: &SavedData,
+        intervention_engine: &str,
+        fonts: &Fonts,
+    ) -> Vec<Shape> {
+        match overlay {
+            EOverlay::Contours | EOverlay::Coastline => overlay::contours::get_contour_shapes(
+                to_screen,
+                clip,
+                &self.dimensions,
+                &self.heights,
+                &settings.contour_settings,
+                overlay == EOverlay::Coastline,
+                fonts,
+            ),
+            EOverlay::References => overlay::references::get_reference_shapes(
+                to_screen,
+                clip,
+                &self.dimensions,
+                &self.cell_records,
+                &self.object_types,
+                &settings.reference_filter,
+            ),
+            _ => self.get_overlay_shapes(overlay, to_screen, settings, intervention_engine, fonts),
+        }
+    }
+
+    /// Marker shapes of an interior's references, by object type
+    pub fn get_interior_layers(
+        &self,
+        interior: &str,
+        to_screen: RectTransform,
+    ) -> Vec<(EObjectType, Vec<Shape>)> {
+        match self.interior_records.get(interior) {
+            Some(cell) => get_interior_layers(to_screen, cell, &self.object_types),
+            None => vec![],
+        }
+    }
+
+    /// Transform from an interior's plan space to an image with the given longest side.
+    /// Returns the transform and the image size
+    pub fn get_interior_transform(&self, interior: &str, size: u32) -> (RectTransform, [u32; 2]) {
+        let bounds = self
+            .interior_records
+            .get(interior)
+            .map(get_interior_bounds)
+            .unwrap_or(Rect::from_min_size(pos2(0.0, 0.0), egui::vec2(1.0, 1.0)));
+        let scale = size as f32 / bounds.width().max(bounds.height());
+        let width = (bounds.width() * scale).round().max(1.0) as u32;
+        let height = (bounds.height() * scale).round().max(1.0) as u32;
+
+        let to = Rect::from_min_max(pos2(0.0, 0.0), pos2(width as f32, height as f32));
+        (RectTransform::from_to(bounds, to), [width, height])
+    }
+
+    /// Draw the plan of an interior with the given longest side
+    pub fn compose_interior_image(&self, interior: &str, size: u32) -> RgbaImage {
+        let (transform, [width, height]) = self.get_interior_transform(interior, size);
+        let mut image = RgbaImage::from_pixel(width, height, image::Rgba([30, 30, 30, 255]));
+        for (_, shapes) in self.get_interior_layers(interior, transform) {
+            draw_shapes(&mut image, shapes);
+        }
+        image
+    }
+
+    /// Transform from cell space to an image of the given size
+    pub fn get_image_transform(&self, width: u32, height: u32) -> RectTransform {
+        let real_width = self.dimensions.width() as f32;
+        let real_height = self.dimensions.height() as f32;
+        let from: Rect = Rect::from_min_max(pos2(0.0, 0.0), pos2(real_width, real_height));
+
+        RectTransform::from_to(
+            from,
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(width as f32, height as f32)),
+        )
+    }
+
+    /// Render a single overlay to a transparent image of the given size
+    pub fn render_overlay(
+        &self,
+        overlay: EOverlay,
+        width: u32,
+        height: u32,
+        settings: &SavedData,
+        intervention_engine: &str,
+    ) -> Result<RgbaImage, ImageError> {
+        let mut image = RgbaImage::new(width, height);
+        self.draw_overlay(&mut image, overlay, settings, intervention_engine)?;
+        Ok(image)
+    }
+
+    /// Draw an overlay on top of an image
+    fn draw_overlay(
+        &self,
+        image: &mut RgbaImage,
+        overlay: EOverlay,
+        settings: &SavedData,
+        intervention_engine: &str,
+    ) -> Result<(), ImageError> {
+        if overlay == EOverlay::Paths {
+            let fg = get_overlay_path_image(&self.dimensions, &self.land_records);
+            let mut fg_image = color_image_to_rgba_image(&fg)?;
+            if fg_image.dimensions() != image.dimensions() {
+                fg_image = imageops::resize(
+                    &fg_image,
+                    image.width(),
+                    image.height(),
+                    imageops::FilterType::CatmullRom,
+                );
+            }
+            imageops::overlay(image, &fg_image, 0, 0);
+        } else {
+            let transform = self.get_image_transform(image.width(), image.height());
+            let shapes = self.get_overlay_shapes(
+                overlay,
+                transform,
+                settings,
+                intervention_engine,
+                export_fonts(),
+            );
+            draw_shapes(image, shapes);
+        }
+        Ok(())
+    }
+
+    /// Render the background with all enabled overlays into one image.
+    /// Without a background the overlays are drawn onto a transparent canvas
+    pub fn compose_image(
+        &self,
+        settings: &SavedData,
+        texture_map: &HashMap<String, ImageBuffer>,
+        intervention_engine: &str,
+    ) -> Result<RgbaImage, ImageError> {
+        let path_size = self.dimensions.pixel_size_tuple(VERTEX_CNT);
+        let mut bg_image = match self.get_background_image(settings.background, settings, texture_map)
+        {
+            Some(bg) => color_image_to_rgba_image(&bg)?,
+            None => RgbaImage::new(path_size[0] as u32, path_size[1] as u32),
+        };
+
+        // overlay paths
+        if settings.overlay_paths {
+            // resize the smaller image to the larger image
+            if [bg_image.width() as usize, bg_image.height() as usize] < path_size {
+                bg_image = imageops::resize(
+                    &bg_image,
+                    path_size[0] as u32,
+                    path_size[1] as u32,
+                    imageops::FilterType::CatmullRom,
+                );
+            }
+            self.draw_overlay(&mut bg_image, EOverlay::Paths, settings, intervention_engine)?;
+        }
+
+        // other overlays, in the order of EOverlay::ALL
+        for overlay in settings.get_overlays() {
+            if overlay != EOverlay::Paths {
+                self.draw_overlay(&mut bg_image, overlay, settings, intervention_engine)?;
+            }
+        }
+
+        Ok(bg_image)
+    }
+}
+
+/// Id of a plugin in reference keys, from its lowercased file name.
+/// Unlike the load order index it stays the same when plugins are added or reordered
+pub fn get_plugin_origin(name: &str) -> u32 {
+    seahash::hash(name.to_lowercase().as_bytes()) as u32
+}
+
+/// Reference key across plugins: (origin of the plugin that added it, refr index), see get_plugin_origin.
+/// A plugin's mast_index is 0 for its own references, otherwise 1 + the index in its masters
+pub fn get_reference_key(
+    (mast_index, refr_index): (u32, u32),
+    origin: u32,
+    masters: &[u32],
+) -> (u32, u32) {
+    let origin = match mast_index {
+        0 => origin,
+        m => masters.get(m as usize - 1).copied().unwrap_or(u32::MAX),
+    };
+    (origin, refr_index)
+}
+
+/// Merge a plugin's version of a cell into the loaded one.
+/// Plugins only store the references they add or change, the rest of the record is replaced
+fn merge_cell(existing: Option<&Cell>, cell: &Cell, origin: u32, masters: &[u32]) -> Cell {
+    let mut merged = cell.clone();
+    merged.references = existing.map(|c| c.references.clone()).unwrap_or_default();
+    for (key, reference) in &cell.references {
+        let key = get_reference_key(*key, origin, masters);
+        merged.references.insert(key, reference.clone());
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compose_without_background_is_transparent() {
+        let dimensions = Dimensions {
+            min_x: 0,
+            max_x: 1,
+            min_y: 0,
+            max_y: 1,
+            min_z: 0.0,
+            max_z: 1.0,
+        };
+        let world = WorldData {
+            heights: vec![0.0; dimensions.pixel_size(VERTEX_CNT)],
+            dimensions,
+            ..Default::default()
+        };
+        let settings = SavedData {
+            background: EBackground::None,
+            overlay_grid: true,
+            ..Default::default()
+        };
+
+        let image = world.compose_image(&settings, &HashMap::default(), "").unwrap();
+
+        assert_eq!(image.dimensions(), (2 * VERTEX_CNT as u32, 2 * VERTEX_CNT as u32));
+        assert!(image.pixels().any(|p| p[3] == 0));
+        assert!(image.pixels().any(|p| p[3] > 0));
+    }
+}